@@ -15,6 +15,12 @@ async fn main() {
         let start = plugin.rfind('/').map(|v| v + 1).unwrap_or(0);
         let cmd = &plugin.as_str()[start..];
 
+        // Queries the running daemon, whose log is left alone
+        if cmd == "pop-launcher" && std::env::args().nth(1).as_deref() == Some("stats") {
+            service::stats::main().await;
+            return;
+        }
+
         init_logging(cmd);
 
         match cmd {
@@ -22,7 +28,13 @@ async fn main() {
             "desktop-entries" => plugins::desktop_entries::main().await,
             "find" => plugins::find::main().await,
             "files" => plugins::files::main().await,
-            "pop-launcher" => service::main().await,
+            "pop-launcher" => {
+                if std::env::args().skip(1).any(|arg| arg == "--daemon") {
+                    service::daemon::main(plugins::BUILTINS).await
+                } else {
+                    service::main(plugins::BUILTINS).await
+                }
+            }
             "pop-shell" => plugins::pop_shell::main().await,
             "pulse" => plugins::pulse::main().await,
             "recent" => plugins::recent::main().await,
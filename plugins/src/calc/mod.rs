@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright © 2021 System76
 
+use crate::{Output, Requests};
 use futures::StreamExt;
 use pop_launcher::*;
 use regex::Regex;
@@ -11,10 +12,16 @@ use tokio::{
 };
 
 pub async fn main() {
-    let mut requests = json_input_stream(async_stdin());
+    run(crate::stdin_requests(), Output::default()).await;
+}
+
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
 
     let mut app = App {
         decimal_comma: uses_decimal_comma().await,
+        out,
         ..Default::default()
     };
 
@@ -24,7 +31,10 @@ pub async fn main() {
                 Request::Activate(_) => app.activate().await,
                 Request::ActivateContext { .. } => app.activate_context().await,
                 Request::Context(_) => app.context().await,
-                Request::Search(query) => app.search(&query).await,
+                Request::Search(query) => {
+                    app.out.searching(&query);
+                    app.search(&query).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -37,7 +47,7 @@ pub async fn main() {
 
 pub struct App {
     pub decimal_comma: bool,
-    out: tokio::io::Stdout,
+    out: Output,
     outcome: Option<String>,
     regex: Regex,
 }
@@ -46,7 +56,7 @@ impl Default for App {
     fn default() -> Self {
         Self {
             decimal_comma: false,
-            out: async_stdout(),
+            out: Output::default(),
             outcome: None,
             regex: Regex::new("\\x1B\\[(?:;?[0-9]{1,3})+[mGK]").expect("bad regex for qalc"),
         }
@@ -57,13 +67,13 @@ impl App {
     pub async fn activate(&mut self) {
         if let Some(outcome) = self.outcome.take() {
             let value = ["= ", extract_value(&outcome)].concat();
-            crate::send(&mut self.out, PluginResponse::Fill(value)).await;
+            self.out.send(PluginResponse::Fill(value)).await;
         }
     }
 
     pub async fn activate_context(&mut self) {
         crate::xdg_open("https://qalculate.github.io/manual/qalc.html");
-        crate::send(&mut self.out, PluginResponse::Close).await;
+        self.out.send(PluginResponse::Close).await;
     }
 
     pub async fn context(&mut self) {
@@ -72,7 +82,9 @@ impl App {
             name: "Qalculate! Manual".into(),
         }];
 
-        crate::send(&mut self.out, PluginResponse::Context { id: 0, options }).await;
+        self.out
+            .send(PluginResponse::Context { id: 0, options })
+            .await;
     }
 
     pub async fn search(&mut self, mut query: &str) {
@@ -85,7 +97,14 @@ impl App {
 
         let search = query.trim();
 
-        self.outcome = qcalc(&mut self.regex, search, self.decimal_comma).await;
+        self.outcome = match qcalc(&mut self.regex, search, self.decimal_comma).await {
+            Ok(outcome) => outcome,
+            Err((kind, message)) => {
+                self.out.send(PluginResponse::Error { kind, message }).await;
+                self.out.send(PluginResponse::Finished).await;
+                return;
+            }
+        };
 
         let outcome = self.outcome.clone().or_else(|| {
             if had_prefix {
@@ -96,24 +115,27 @@ impl App {
         });
 
         if let Some(name) = outcome {
-            crate::send(
-                &mut self.out,
-                PluginResponse::Append(PluginSearchResult {
+            self.out
+                .send(PluginResponse::Append(PluginSearchResult {
                     id: 0,
                     name,
                     description: String::new(),
                     icon: Some(IconSource::Name(Cow::Borrowed("accessories-calculator"))),
                     ..Default::default()
-                }),
-            )
-            .await;
+                }))
+                .await;
         }
 
-        crate::send(&mut self.out, PluginResponse::Finished).await;
+        self.out.send(PluginResponse::Finished).await;
     }
 }
 
-async fn qcalc(regex: &mut Regex, expression: &str, decimal_comma: bool) -> Option<String> {
+/// Evaluates the expression with `qalc`, returning its outcome if it has one.
+async fn qcalc(
+    regex: &mut Regex,
+    expression: &str,
+    decimal_comma: bool,
+) -> Result<Option<String>, (ErrorKind, String)> {
     let mut command = Command::new("qalc");
 
     command.args(&["-u8"]);
@@ -135,10 +157,16 @@ async fn qcalc(regex: &mut Regex, expression: &str, decimal_comma: bool) -> Opti
     let mut child = match spawn {
         Ok(child) => child,
         Err(why) => {
-            return Some(if why.kind() == io::ErrorKind::NotFound {
-                String::from("qalc command is not installed")
+            return Err(if why.kind() == io::ErrorKind::NotFound {
+                (
+                    ErrorKind::MissingDependency,
+                    String::from("qalc command is not installed"),
+                )
             } else {
-                format!("qalc command failed to spawn: {}", why)
+                (
+                    ErrorKind::Failed,
+                    format!("qalc command failed to spawn: {}", why),
+                )
             })
         }
     };
@@ -152,8 +180,9 @@ async fn qcalc(regex: &mut Regex, expression: &str, decimal_comma: bool) -> Opti
     let stdout = match child.stdout.take() {
         Some(stdout) => stdout,
         None => {
-            return Some(String::from(
-                "qalc lacks stdout pipe: did you get hit by a cosmic ray?",
+            return Err((
+                ErrorKind::Failed,
+                String::from("qalc lacks stdout pipe: did you get hit by a cosmic ray?"),
             ));
         }
     };
@@ -179,7 +208,7 @@ async fn qcalc(regex: &mut Regex, expression: &str, decimal_comma: bool) -> Opti
         let mut normalized = normalized.as_ref();
 
         if has_issue(normalized) {
-            return None;
+            return Ok(None);
         } else {
             if !output.is_empty() {
                 output.push(' ');
@@ -206,7 +235,7 @@ async fn qcalc(regex: &mut Regex, expression: &str, decimal_comma: bool) -> Opti
             } else if let Some(pos) = normalized.rfind('=') {
                 pos + 1
             } else {
-                return None;
+                return Ok(None);
             };
 
             normalized = normalized[cut..].trim_start();
@@ -218,7 +247,7 @@ async fn qcalc(regex: &mut Regex, expression: &str, decimal_comma: bool) -> Opti
         };
     }
 
-    Some(output)
+    Ok(Some(output))
 }
 
 pub async fn uses_decimal_comma() -> bool {
@@ -10,7 +10,6 @@ use pop_launcher::*;
 use std::borrow::Cow;
 use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
-use tokio::io::AsyncWrite;
 
 #[derive(Debug, Eq)]
 struct Item {
@@ -18,6 +17,8 @@ struct Item {
     description: String,
     exec: String,
     icon: Option<String>,
+    /// The words of the name, keywords, and command, folded for comparison with queries.
+    interests: Vec<String>,
     keywords: Option<Vec<String>>,
     name: String,
     path: PathBuf,
@@ -37,11 +38,31 @@ impl PartialEq for Item {
     }
 }
 
+impl Item {
+    /// Folds the words that queries are compared with, keeping their accents if asked.
+    fn fold(&mut self, keep_accents: bool) {
+        let keywords = self.keywords.iter().flatten().map(String::as_str);
+
+        self.interests = self
+            .name
+            .split_ascii_whitespace()
+            .chain(keywords)
+            .chain(std::iter::once(self.exec.as_str()))
+            .map(|interest| normalize::fold_with(interest, !keep_accents))
+            .collect();
+    }
+}
+
 pub async fn main() {
-    let mut app = App::new(async_stdout());
-    app.reload().await;
+    run(crate::stdin_requests(), Output::default()).await;
+}
 
-    let mut requests = json_input_stream(async_stdin());
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
+
+    let mut app = App::new(out);
+    app.reload().await;
 
     while let Some(result) = requests.next().await {
         match result {
@@ -49,7 +70,10 @@ pub async fn main() {
                 Request::Activate(id) => app.activate(id).await,
                 Request::ActivateContext { id, context } => app.activate_context(id, context).await,
                 Request::Context(id) => app.context(id).await,
-                Request::Search(query) => app.search(&query).await,
+                Request::Search(query) => {
+                    app.tx.searching(&query);
+                    app.search(&query).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -64,18 +88,21 @@ pub async fn main() {
 /// Desktop entries to hard exclude.
 const EXCLUSIONS: &[&str] = &["GNOME Shell", "Initial Setup"];
 
-struct App<W> {
+struct App {
     entries: Vec<Item>,
+    /// Whether the entries were folded with their accents kept.
+    keep_accents: bool,
     locale: Option<String>,
-    tx: W,
+    tx: Output,
 }
 
-impl<W: AsyncWrite + Unpin> App<W> {
-    fn new(tx: W) -> Self {
+impl App {
+    fn new(tx: Output) -> Self {
         let lang = std::env::var("LANG").ok();
 
         Self {
             entries: Vec::new(),
+            keep_accents: false,
             locale: lang
                 .as_ref()
                 .and_then(|l| l.split('.').next())
@@ -150,7 +177,7 @@ impl<W: AsyncWrite + Unpin> App<W> {
                                 continue;
                             }
 
-                            let item = Item {
+                            let mut item = Item {
                                 appid: entry.appid.to_owned(),
                                 name: name.to_string(),
                                 description: entry
@@ -162,12 +189,14 @@ impl<W: AsyncWrite + Unpin> App<W> {
                                     keywords.split(';').map(String::from).collect()
                                 }),
                                 icon: entry.icon().map(|x| x.to_owned()),
+                                interests: Vec::new(),
                                 exec: exec.to_owned(),
                                 path: path.clone(),
                                 prefers_non_default_gpu: entry.prefers_non_default_gpu(),
                                 src,
                             };
 
+                            item.fold(self.keep_accents);
                             deduplicator.insert(item);
                         }
                     }
@@ -189,7 +218,7 @@ impl<W: AsyncWrite + Unpin> App<W> {
                 },
             };
 
-            send(&mut self.tx, response).await;
+            self.tx.send(response).await;
         }
     }
 
@@ -207,7 +236,7 @@ impl<W: AsyncWrite + Unpin> App<W> {
                 _ => return,
             };
 
-            send(&mut self.tx, response).await;
+            self.tx.send(response).await;
         }
     }
 
@@ -230,13 +259,22 @@ impl<W: AsyncWrite + Unpin> App<W> {
             if !options.is_empty() {
                 let response = PluginResponse::Context { id, options };
 
-                send(&mut self.tx, response).await;
+                self.tx.send(response).await;
             }
         }
     }
 
-    async fn search(&mut self, query: &str) {
-        let query = query.to_ascii_lowercase();
+    async fn search(&mut self, query: &Query) {
+        // Entries are folded once, and again only if queries come to keep accents
+        if self.keep_accents != query.keep_accents {
+            self.keep_accents = query.keep_accents;
+
+            for entry in &mut self.entries {
+                entry.fold(query.keep_accents);
+            }
+        }
+
+        let query = normalize::fold_with(query, !query.keep_accents);
 
         let &mut Self {
             ref entries,
@@ -244,24 +282,13 @@ impl<W: AsyncWrite + Unpin> App<W> {
             ..
         } = self;
 
-        let mut items = Vec::with_capacity(16);
-
         for (id, entry) in entries.iter().enumerate() {
-            items.extend(entry.name.split_ascii_whitespace());
-
-            if let Some(keywords) = entry.keywords.as_ref() {
-                items.extend(keywords.iter().map(String::as_str));
-            }
-
-            items.push(entry.exec.as_str());
-
-            for search_interest in items.drain(..) {
-                let search_interest = search_interest.to_ascii_lowercase();
+            for search_interest in &entry.interests {
                 let append = search_interest.starts_with(&*query)
                     || query
                         .split_ascii_whitespace()
                         .any(|query| search_interest.contains(&*query))
-                    || strsim::jaro_winkler(&*query, &*search_interest) > 0.6;
+                    || strsim::jaro_winkler(&*query, search_interest) > 0.6;
 
                 if append {
                     let desc_source = path_string(&entry.src);
@@ -277,17 +304,18 @@ impl<W: AsyncWrite + Unpin> App<W> {
                         keywords: entry.keywords.clone(),
                         icon: entry.icon.clone().map(Cow::Owned).map(IconSource::Name),
                         exec: Some(entry.exec.clone()),
+                        dedupe_key: Some(entry.appid.clone()),
                         ..Default::default()
                     });
 
-                    send(tx, response).await;
+                    tx.send(response).await;
 
                     break;
                 }
             }
         }
 
-        send(tx, PluginResponse::Finished).await;
+        tx.send(PluginResponse::Finished).await;
     }
 }
 
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright © 2021 System76
 
+use crate::{Output, Requests};
 use futures::prelude::*;
 use pop_launcher::*;
 use std::{collections::BTreeMap, path::PathBuf};
@@ -9,21 +10,34 @@ use std::{collections::BTreeMap, path::PathBuf};
 struct Item {
     path: PathBuf,
     name: String,
+    /// The name, folded for comparison with queries.
+    folded: String,
     description: String,
     icon: IconSource,
 }
 
 pub async fn main() {
-    let mut requests = json_input_stream(async_stdin());
+    run(crate::stdin_requests(), Output::default()).await;
+}
+
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
 
-    let mut app = App::default();
+    let mut app = App {
+        out,
+        ..App::default()
+    };
 
     while let Some(result) = requests.next().await {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
                 Request::Complete(id) => app.complete(id).await,
-                Request::Search(query) => app.search(query).await,
+                Request::Search(query) => {
+                    app.out.searching(&query);
+                    app.search(query).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -37,7 +51,9 @@ pub async fn main() {
 pub struct App {
     entries: BTreeMap<PathBuf, Vec<Item>>,
     home: PathBuf,
-    out: tokio::io::Stdout,
+    /// Whether the names of entries were folded with their accents kept.
+    keep_accents: bool,
+    out: Output,
     search_results: Vec<Item>,
 }
 
@@ -46,7 +62,8 @@ impl Default for App {
         Self {
             entries: BTreeMap::default(),
             home: dirs::home_dir().expect("no home dir"),
-            out: async_stdout(),
+            keep_accents: false,
+            out: Output::default(),
             search_results: Vec::with_capacity(100),
         }
     }
@@ -56,7 +73,7 @@ impl App {
     pub async fn activate(&mut self, id: u32) {
         if let Some(selected) = self.search_results.get(id as usize) {
             crate::xdg_open(&selected.path);
-            crate::send(&mut self.out, PluginResponse::Close).await;
+            self.out.send(PluginResponse::Close).await;
         }
     }
 
@@ -72,16 +89,27 @@ impl App {
                 let suffix = if path.is_dir() { "/" } else { "" };
                 let fill = [prefix, string, suffix].concat();
 
-                crate::send(&mut self.out, PluginResponse::Fill(fill)).await;
+                self.out.send(PluginResponse::Fill(fill)).await;
             }
         }
     }
 
-    pub async fn search(&mut self, query: String) {
+    pub async fn search(&mut self, query: Query) {
+        let keep_accents = query.keep_accents;
+
+        // Names are folded once, and again only if queries come to keep accents
+        if self.keep_accents != keep_accents {
+            self.keep_accents = keep_accents;
+
+            for item in self.entries.values_mut().flatten() {
+                item.folded = normalize::fold_with(&item.name, !keep_accents);
+            }
+        }
+
         let path = if let Some(stripped) = query.strip_prefix("~/") {
             self.home.join(stripped)
         } else {
-            PathBuf::from(query)
+            PathBuf::from(query.text)
         };
 
         let mut show_hidden = false;
@@ -89,7 +117,7 @@ impl App {
 
         if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
             show_hidden = filename.starts_with('.');
-            base = filename.to_ascii_lowercase();
+            base = normalize::fold_with(filename, !keep_accents);
         }
 
         self.search_results.clear();
@@ -110,6 +138,7 @@ impl App {
                             items.push(Item {
                                 icon: IconSource::Mime(crate::mime_from_path(&path)),
                                 name: name.to_owned(),
+                                folded: normalize::fold_with(name, !keep_accents),
                                 description: path
                                     .metadata()
                                     .ok()
@@ -141,8 +170,8 @@ impl App {
         use std::cmp::Ordering;
 
         self.search_results.sort_by(|a, b| {
-            let a_name = a.name.to_ascii_lowercase();
-            let b_name = b.name.to_ascii_lowercase();
+            let a_name = &a.folded;
+            let b_name = &b.folded;
 
             let a_contains = a_name.contains(&base);
             let b_contains = b_name.contains(&base);
@@ -153,7 +182,7 @@ impl App {
                 } else if b_name.starts_with(&base) {
                     Ordering::Greater
                 } else {
-                    human_sort::compare(&a_name, &b_name)
+                    human_sort::compare(a_name, b_name)
                 }
             } else if a_contains {
                 Ordering::Less
@@ -165,23 +194,22 @@ impl App {
         });
 
         for (id, selection) in self.search_results.iter().enumerate() {
-            crate::send(
-                &mut self.out,
-                PluginResponse::Append(PluginSearchResult {
+            self.out
+                .send(PluginResponse::Append(PluginSearchResult {
                     id: id as u32,
                     name: selection.name.clone(),
                     description: selection.description.clone(),
                     icon: Some(selection.icon.clone()),
+                    dedupe_key: crate::file_uri(&selection.path),
                     ..Default::default()
-                }),
-            )
-            .await;
+                }))
+                .await;
 
             if id == 19 {
                 break;
             }
         }
 
-        crate::send(&mut self.out, PluginResponse::Finished).await;
+        self.out.send(PluginResponse::Finished).await;
     }
 }
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright © 2021 System76
 
+use crate::{Output, Requests};
 use futures::*;
 use pop_launcher::*;
 use std::cell::Cell;
@@ -14,10 +15,15 @@ use tokio::process::{Child, ChildStdout, Command};
 #[derive(Debug)]
 enum Event {
     Activate(u32),
-    Search(String),
+    Search(Query),
 }
 
 pub async fn main() {
+    run(crate::stdin_requests(), Output::default()).await;
+}
+
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, out: Output) {
     let (event_tx, event_rx) = flume::bounded::<Event>(8);
 
     // Channel for cancelling searches that are in progress.
@@ -30,11 +36,13 @@ pub async fn main() {
         search_results: Vec::with_capacity(128),
         active: active.clone(),
         interrupt_rx,
-        out: async_stdout(),
+        out,
     };
 
     // Manages the external process, tracks search results, and executes activate requests
     let search_handler = async move {
+        app.out.hello().await;
+
         while let Ok(search) = event_rx.recv_async().await {
             match search {
                 Event::Activate(id) => {
@@ -44,14 +52,14 @@ pub async fn main() {
                             crate::xdg_open(&path);
                         });
 
-                        crate::send(&mut app.out, PluginResponse::Close).await;
+                        app.out.send(PluginResponse::Close).await;
                     }
                 }
 
                 Event::Search(search) => {
                     app.search(search).await;
                     app.active.set(false);
-                    crate::send(&mut app.out, PluginResponse::Finished).await;
+                    app.out.send(PluginResponse::Finished).await;
                 }
             }
         }
@@ -69,8 +77,6 @@ pub async fn main() {
             }
         };
 
-        let mut requests = json_input_stream(async_stdin());
-
         while let Some(result) = requests.next().await {
             match result {
                 Ok(request) => match request {
@@ -85,13 +91,7 @@ pub async fn main() {
                     // Schedule a new search process to be launched
                     Request::Search(query) => {
                         interrupt().await;
-
-                        let query = match query.find(' ') {
-                            Some(pos) => query[pos..].trim_start(),
-                            None => &query,
-                        };
-
-                        event_tx.send_async(Event::Search(query.to_owned())).await?;
+                        event_tx.send_async(Event::Search(query)).await?;
                         active.set(true);
                     }
 
@@ -114,7 +114,7 @@ pub async fn main() {
 struct SearchContext {
     pub active: Rc<Cell<bool>>,
     pub interrupt_rx: flume::Receiver<()>,
-    pub out: tokio::io::Stdout,
+    pub out: Output,
     pub search_results: Vec<PathBuf>,
 }
 
@@ -140,34 +140,36 @@ impl SearchContext {
             description,
             name,
             icon: Some(IconSource::Mime(crate::mime_from_path(&path))),
+            dedupe_key: crate::file_uri(&path),
             ..Default::default()
         });
 
-        crate::send(&mut self.out, response).await;
+        self.out.send(response).await;
         self.search_results.push(path);
     }
 
     /// Submits the query to `fdfind` and actively monitors the search results while handling interrupts.
-    async fn search(&mut self, search: String) {
+    async fn search(&mut self, search: Query) {
+        self.out.searching(&search);
         self.search_results.clear();
         let (mut child, mut stdout) = match query(&search).await {
             Ok((child, stdout)) => (child, tokio::io::BufReader::new(stdout).lines()),
             Err(why) => {
                 tracing::error!("failed to spawn fdfind process: {}", why);
 
-                let _ = crate::send(
-                    &mut self.out,
-                    PluginResponse::Append(PluginSearchResult {
-                        id: 0,
-                        name: if why.kind() == io::ErrorKind::NotFound {
-                            String::from("fdfind command is not installed")
-                        } else {
-                            format!("failed to spawn fdfind process: {}", why)
-                        },
-                        ..Default::default()
-                    }),
-                )
-                .await;
+                let response = if why.kind() == io::ErrorKind::NotFound {
+                    PluginResponse::Error {
+                        kind: ErrorKind::MissingDependency,
+                        message: String::from("fdfind command is not installed"),
+                    }
+                } else {
+                    PluginResponse::Error {
+                        kind: ErrorKind::Failed,
+                        message: format!("failed to spawn fdfind process: {}", why),
+                    }
+                };
+
+                self.out.send(response).await;
 
                 return;
             }
@@ -12,10 +12,135 @@ pub mod scripts;
 pub mod terminal;
 pub mod web;
 
-use pop_launcher::PluginResponse;
+use futures::future::LocalBoxFuture;
+use futures::stream::{BoxStream, StreamExt};
+use pop_launcher::{
+    async_stdin, async_stdout, json_input_stream, Capabilities, PluginResponse, Query, QueryId,
+    Request, PROTOCOL_VERSION,
+};
 use std::{borrow::Cow, ffi::OsStr, future::Future, path::Path};
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
+/// Requests received by a plugin, which are errors if they could not be decoded.
+pub type Requests = BoxStream<'static, serde_json::Result<Request>>;
+
+/// Runs a plugin within the launcher service, given the channels which its
+/// requests are received from and its responses are sent to.
+pub type Builtin =
+    fn(flume::Receiver<Request>, flume::Sender<PluginResponse>) -> LocalBoxFuture<'static, ()>;
+
+/// Plugins which may run within the launcher service, by the command which runs
+/// them in a process of their own.
+///
+/// The terminal plugin is absent, as it exits its process after launching a command.
+pub const BUILTINS: &[(&str, Builtin)] = &[
+    ("calc", |rx, tx| {
+        Box::pin(calc::run(channel_requests(rx), Output::channel(tx)))
+    }),
+    ("desktop-entries", |rx, tx| {
+        Box::pin(desktop_entries::run(
+            channel_requests(rx),
+            Output::channel(tx),
+        ))
+    }),
+    ("files", |rx, tx| {
+        Box::pin(files::run(channel_requests(rx), Output::channel(tx)))
+    }),
+    ("find", |rx, tx| {
+        Box::pin(find::run(channel_requests(rx), Output::channel(tx)))
+    }),
+    ("pop-shell", |rx, tx| {
+        Box::pin(pop_shell::run(channel_requests(rx), Output::channel(tx)))
+    }),
+    ("pulse", |rx, tx| {
+        Box::pin(pulse::run(channel_requests(rx), Output::channel(tx)))
+    }),
+    ("recent", |rx, tx| {
+        Box::pin(recent::run(channel_requests(rx), Output::channel(tx)))
+    }),
+    ("scripts", |rx, tx| {
+        Box::pin(scripts::run(channel_requests(rx), Output::channel(tx)))
+    }),
+    ("web", |rx, tx| {
+        Box::pin(web::run(channel_requests(rx), Output::channel(tx)))
+    }),
+];
+
+/// Where a plugin sends its responses to the launcher service.
+pub struct Output {
+    sink: Sink,
+    /// ID of the query being searched, which is echoed in the results sent
+    query_id: Option<QueryId>,
+}
+
+enum Sink {
+    /// Serialized to stdout, when the plugin runs in a process of its own.
+    Stdout(tokio::io::Stdout),
+    /// Sent over a channel, when the plugin runs within the service.
+    Channel(flume::Sender<PluginResponse>),
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self {
+            sink: Sink::Stdout(async_stdout()),
+            query_id: None,
+        }
+    }
+}
+
+impl Output {
+    /// Sends responses over a channel, when the plugin runs within the service.
+    pub fn channel(tx: flume::Sender<PluginResponse>) -> Self {
+        Self {
+            sink: Sink::Channel(tx),
+            query_id: None,
+        }
+    }
+
+    /// Tells the service what the plugin understands, such that it tags searches
+    /// with their IDs.
+    pub async fn hello(&mut self) {
+        let capabilities = Capabilities {
+            version: PROTOCOL_VERSION,
+            query_id: true,
+            ..Capabilities::default()
+        };
+
+        self.send(PluginResponse::Hello(capabilities)).await;
+    }
+
+    /// Echoes the ID of the query in the results sent, until the next search.
+    pub fn searching(&mut self, query: &Query) {
+        self.query_id = query.id;
+    }
+
+    pub async fn send(&mut self, mut response: PluginResponse) {
+        if let PluginResponse::Append(result) = &mut response {
+            if result.query_id.is_none() {
+                result.query_id = self.query_id;
+            }
+        }
+
+        match &mut self.sink {
+            Sink::Stdout(stdout) => send(stdout, response).await,
+            Sink::Channel(tx) => {
+                let _ = tx.send_async(response).await;
+            }
+        }
+    }
+}
+
+/// Requests of a plugin running in a process of its own, decoded from stdin.
+pub fn stdin_requests() -> Requests {
+    json_input_stream(async_stdin()).boxed()
+}
+
+/// Requests of a plugin running within the launcher service.
+pub fn channel_requests(requests: flume::Receiver<Request>) -> Requests {
+    requests.into_stream().map(Ok).boxed()
+}
+
 pub async fn send<W: AsyncWrite + Unpin>(tx: &mut W, response: PluginResponse) {
     if let Ok(mut bytes) = serde_json::to_string(&response) {
         bytes.push('\n');
@@ -45,7 +170,42 @@ pub fn mime_from_path(path: &Path) -> Cow<'static, str> {
     }
 }
 
+/// The `file://` URI of a path, which identifies results referring to the same file.
+pub fn file_uri(path: &Path) -> Option<String> {
+    let path = std::path::absolute(path).ok()?;
+    url::Url::from_file_path(path).ok().map(String::from)
+}
+
 /// Launches a file with its default appplication via `xdg-open`.
 pub fn xdg_open<S: AsRef<OsStr>>(file: S) {
     let _ = tokio::process::Command::new("xdg-open").arg(file).spawn();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pop_launcher::PluginSearchResult;
+
+    #[tokio::test]
+    async fn results_echo_query_id() {
+        let (tx, rx) = flume::unbounded();
+        let mut out = Output::channel(tx);
+
+        out.searching(&Query {
+            text: String::from("fire"),
+            id: Some(7),
+            trigger: None,
+            keep_accents: false,
+        });
+
+        out.send(PluginResponse::Append(PluginSearchResult::new(
+            0, "Firefox",
+        )))
+        .await;
+
+        match rx.try_recv() {
+            Ok(PluginResponse::Append(result)) => assert_eq!(Some(7), result.query_id),
+            other => panic!("expected an append, received {:?}", other),
+        }
+    }
+}
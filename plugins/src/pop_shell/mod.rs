@@ -7,7 +7,6 @@ use futures::StreamExt;
 use pop_launcher::*;
 use serde::{Deserialize, Serialize};
 use std::{convert::TryFrom, fs, path::PathBuf, sync::Arc};
-use tokio::io::{AsyncWrite, AsyncWriteExt};
 use zbus::Connection;
 use zvariant::{Signature, Type};
 
@@ -29,25 +28,33 @@ impl Type for Item {
 }
 
 pub async fn main() {
+    run(crate::stdin_requests(), Output::default()).await;
+}
+
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
+
     let connection = match Connection::session().await {
         Ok(conn) => conn,
         Err(_) => {
-            let mut out = async_stdout();
-            let _ = crate::send(&mut out, PluginResponse::Deactivate);
+            let _ = out.send(PluginResponse::Deactivate);
             return;
         }
     };
 
-    let mut app = App::new(connection, async_stdout());
+    let mut app = App::new(connection, out);
     app.reload().await;
 
-    let mut requests = json_input_stream(async_stdin());
     while let Some(request) = requests.next().await {
         match request {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
                 Request::Quit(id) => app.quit(id).await,
-                Request::Search(query) => app.search(&query).await,
+                Request::Search(query) => {
+                    app.tx.searching(&query);
+                    app.search(&query).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -58,15 +65,15 @@ pub async fn main() {
     }
 }
 
-struct App<W> {
+struct App {
     desktop_entries: Vec<(fde::PathSource, PathBuf)>,
     entries: Vec<Item>,
     connection: Connection,
-    tx: W,
+    tx: Output,
 }
 
-impl<W: AsyncWrite + Unpin> App<W> {
-    fn new(connection: Connection, tx: W) -> Self {
+impl App {
+    fn new(connection: Connection, tx: Output) -> Self {
         Self {
             desktop_entries: fde::Iter::new(fde::default_paths())
                 .map(|path| (fde::PathSource::guess_from(&path), path))
@@ -109,18 +116,19 @@ impl<W: AsyncWrite + Unpin> App<W> {
         }
     }
 
-    async fn search(&mut self, query: &str) {
-        let query = query.to_ascii_lowercase();
+    async fn search(&mut self, query: &Query) {
+        let fold = |text: &str| normalize::fold_with(text, !query.keep_accents);
+
+        let query = fold(query);
         let haystack = query.split_ascii_whitespace().collect::<Vec<&str>>();
 
-        fn contains_pattern(needle: &str, haystack: &[&str]) -> bool {
-            let needle = needle.to_ascii_lowercase();
+        let contains_pattern = |needle: &str| {
+            let needle = fold(needle);
             haystack.iter().all(|h| needle.contains(h))
-        }
+        };
 
         for (id, item) in self.entries.iter().enumerate() {
-            let retain = contains_pattern(&item.name, &haystack)
-                || contains_pattern(&item.description, &haystack);
+            let retain = contains_pattern(&item.name) || contains_pattern(&item.description);
 
             if !retain {
                 continue;
@@ -146,21 +154,18 @@ impl<W: AsyncWrite + Unpin> App<W> {
                 }
             }
 
-            send(
-                &mut self.tx,
-                PluginResponse::Append(PluginSearchResult {
+            self.tx
+                .send(PluginResponse::Append(PluginSearchResult {
                     id: id as u32,
                     name: item.name.clone(),
                     description: item.description.clone(),
                     icon: Some(IconSource::Name(icon_name)),
                     window: Some(item.entity),
                     ..Default::default()
-                }),
-            )
-            .await;
+                }))
+                .await;
         }
 
-        send(&mut self.tx, PluginResponse::Finished).await;
-        let _ = self.tx.flush();
+        self.tx.send(PluginResponse::Finished).await;
     }
 }
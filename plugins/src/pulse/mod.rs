@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright © 2021 System76
 
+use crate::{Output, Requests};
 use async_pidfd::AsyncPidFd;
 use futures::prelude::*;
 use pop_launcher::*;
@@ -14,13 +15,13 @@ struct Selection {
 
 pub struct App {
     selections: Vec<Selection>,
-    out: tokio::io::Stdout,
+    out: Output,
 }
 
 impl Default for App {
     fn default() -> Self {
         Self {
-            out: async_stdout(),
+            out: Output::default(),
             selections: vec![
                 Selection {
                     id: 0,
@@ -43,15 +44,26 @@ impl Default for App {
 }
 
 pub async fn main() {
-    let mut requests = json_input_stream(async_stdin());
+    run(crate::stdin_requests(), Output::default()).await;
+}
+
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
 
-    let mut app = App::default();
+    let mut app = App {
+        out,
+        ..App::default()
+    };
 
     while let Some(result) = requests.next().await {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
-                Request::Search(query) => app.search(query).await,
+                Request::Search(query) => {
+                    app.out.searching(&query);
+                    app.search(query).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -87,33 +99,36 @@ impl App {
         }
     }
 
-    async fn search(&mut self, query: String) {
+    async fn search(&mut self, query: Query) {
         if !query.is_empty() {
-            for selection in filter(&self.selections, &query.to_ascii_lowercase()) {
-                crate::send(
-                    &mut self.out,
-                    PluginResponse::Append(PluginSearchResult {
+            let keep_accents = query.keep_accents;
+            let query = normalize::fold_with(&query, !keep_accents);
+
+            for selection in filter(&self.selections, &query, keep_accents) {
+                self.out
+                    .send(PluginResponse::Append(PluginSearchResult {
                         id: selection.id,
                         name: selection.name.clone(),
                         description: selection.description.clone(),
                         ..Default::default()
-                    }),
-                )
-                .await;
+                    }))
+                    .await;
             }
         }
 
-        crate::send(&mut self.out, PluginResponse::Finished).await;
+        self.out.send(PluginResponse::Finished).await;
     }
 }
 
 fn filter<'a>(
     selections: &'a [Selection],
     query: &'a str,
+    keep_accents: bool,
 ) -> impl Iterator<Item = &'a Selection> + 'a {
+    let fold = move |text: &str| normalize::fold_with(text, !keep_accents);
+
     selections.iter().filter(move |selection| {
-        selection.name.to_ascii_lowercase().contains(query)
-            || selection.description.to_ascii_lowercase().contains(query)
+        fold(&selection.name).contains(query) || fold(&selection.description).contains(query)
     })
 }
 
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright © 2021 System76
 
+use crate::{Output, Requests};
 use futures::prelude::*;
 use pop_launcher::*;
 use recently_used_xbel::{parse_file, RecentlyUsed};
@@ -8,30 +9,45 @@ use slab::Slab;
 use std::borrow::Cow;
 
 pub struct App {
+    /// Why the recently used files could not be read, reported with the next search
+    failure: Option<String>,
     recent: Option<RecentlyUsed>,
-    out: tokio::io::Stdout,
+    out: Output,
     uris: Slab<String>,
 }
 
 impl Default for App {
     fn default() -> Self {
         Self {
+            failure: None,
             recent: None,
-            out: async_stdout(),
+            out: Output::default(),
             uris: Slab::new(),
         }
     }
 }
 
 pub async fn main() {
-    let mut requests = json_input_stream(async_stdin());
+    run(crate::stdin_requests(), Output::default()).await;
+}
+
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
 
-    let mut app = App::default();
+    let mut app = App {
+        out,
+        ..App::default()
+    };
 
     match parse_file() {
         Ok(recent) => app.recent = Some(recent),
+        Err(recently_used_xbel::Error::DoesNotExist) => {
+            tracing::info!("no recently used files to search");
+        }
         Err(why) => {
             tracing::error!("failed to parse recently used files: {}", why);
+            app.failure = Some(format!("failed to parse recently used files: {}", why));
         }
     }
 
@@ -39,7 +55,10 @@ pub async fn main() {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
-                Request::Search(query) => app.search(query).await,
+                Request::Search(query) => {
+                    app.out.searching(&query);
+                    app.search(query).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -54,13 +73,26 @@ impl App {
     async fn activate(&mut self, id: u32) {
         if let Some(uri) = self.uris.get(id as usize) {
             crate::xdg_open(uri);
-            crate::send(&mut self.out, PluginResponse::Close).await;
+            self.out.send(PluginResponse::Close).await;
         }
     }
 
-    async fn search(&mut self, query: String) {
+    async fn search(&mut self, query: Query) {
         self.uris.clear();
-        if let Some((recent, query)) = self.recent.as_ref().zip(normalized(&query)) {
+
+        if let Some(message) = self.failure.take() {
+            self.out
+                .send(PluginResponse::Error {
+                    kind: ErrorKind::Failed,
+                    message,
+                })
+                .await;
+        }
+
+        if let Some(recent) = self.recent.as_ref() {
+            let keep_accents = query.keep_accents;
+            let query = normalize::fold_with(query.trim(), !keep_accents);
+
             for item in recent.bookmarks.iter().rev() {
                 let display_uri = item.href.replace("%20", " ");
 
@@ -69,22 +101,21 @@ impl App {
                     None => &display_uri,
                 };
 
-                let lowername = name.to_ascii_lowercase();
+                let lowername = normalize::fold_with(name, !keep_accents);
 
                 if query.split_whitespace().all(|key| lowername.contains(key)) {
                     if let Some(mime) = new_mime_guess::from_path(&item.href).first() {
                         let id = self.uris.insert(item.href.clone());
-                        crate::send(
-                            &mut self.out,
-                            PluginResponse::Append(PluginSearchResult {
+                        self.out
+                            .send(PluginResponse::Append(PluginSearchResult {
                                 id: id as u32,
                                 name: name.to_owned(),
                                 description: display_uri,
                                 icon: Some(IconSource::Mime(Cow::Owned(mime.to_string()))),
+                                dedupe_key: url::Url::parse(&item.href).ok().map(String::from),
                                 ..Default::default()
-                            }),
-                        )
-                        .await;
+                            }))
+                            .await;
 
                         if id == 19 {
                             break;
@@ -94,12 +125,6 @@ impl App {
             }
         }
 
-        crate::send(&mut self.out, PluginResponse::Finished).await;
+        self.out.send(PluginResponse::Finished).await;
     }
 }
-
-fn normalized(input: &str) -> Option<String> {
-    input
-        .find(' ')
-        .map(|pos| input[pos + 1..].trim().to_ascii_lowercase())
-}
@@ -17,9 +17,14 @@ const SYSTEM_ADMIN_PATH: &str = "/etc/pop-launcher/scripts";
 const DISTRIBUTION_PATH: &str = "/usr/lib/pop-launcher/scripts";
 
 pub async fn main() {
-    let mut requests = json_input_stream(async_stdin());
+    run(crate::stdin_requests(), Output::default()).await;
+}
+
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
 
-    let mut app = App::new();
+    let mut app = App::new(out);
 
     app.reload().await;
 
@@ -27,7 +32,10 @@ pub async fn main() {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
-                Request::Search(query) => app.search(&query).await,
+                Request::Search(query) => {
+                    app.out.searching(&query);
+                    app.search(&query).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -41,21 +49,24 @@ pub async fn main() {
 
 pub struct App {
     scripts: Vec<ScriptInfo>,
-    out: tokio::io::Stdout,
+    /// Whether the scripts were folded with their accents kept.
+    keep_accents: bool,
+    out: Output,
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(out: Output) -> Self {
         App {
             scripts: Vec::with_capacity(16),
-            out: async_stdout(),
+            keep_accents: false,
+            out,
         }
     }
 
     async fn activate(&mut self, id: u32) {
         if let Some(script) = self.scripts.get(id as usize) {
             let interpreter = script.interpreter.as_deref().unwrap_or("sh");
-            send(&mut self.out, PluginResponse::Close).await;
+            self.out.send(PluginResponse::Close).await;
 
             let _ = Command::new(interpreter)
                 .arg(script.path.as_os_str())
@@ -85,14 +96,17 @@ impl App {
             }
         };
 
+        let keep_accents = self.keep_accents;
+
         let script_receiver = async {
-            'outer: while let Ok(script) = rx.recv_async().await {
+            'outer: while let Ok(mut script) = rx.recv_async().await {
                 tracing::debug!("appending script: {:?}", script);
                 for cached_script in &self.scripts {
                     if cached_script.name == script.name {
                         continue 'outer;
                     }
                 }
+                script.fold(keep_accents);
                 self.scripts.push(script);
             }
         };
@@ -100,48 +114,70 @@ impl App {
         futures::future::join(script_sender, script_receiver).await;
     }
 
-    async fn search(&mut self, query: &str) {
+    async fn search(&mut self, query: &Query) {
+        // Scripts are folded once, and again only if queries come to keep accents
+        if self.keep_accents != query.keep_accents {
+            self.keep_accents = query.keep_accents;
+
+            for script in &mut self.scripts {
+                script.fold(query.keep_accents);
+            }
+        }
+
         let &mut Self {
             ref scripts,
             ref mut out,
             ..
         } = self;
+        let query = &normalize::fold_with(query, !query.keep_accents);
+
         for (id, script) in scripts.iter().enumerate() {
-            let should_include = script.name.to_ascii_lowercase().contains(query)
-                || script.description.to_ascii_lowercase().contains(query)
-                || script.keywords.iter().any(|k| k.contains(query));
+            let should_include = script.folded.iter().any(|text| text.contains(query));
 
             if should_include {
-                send(
-                    out,
-                    PluginResponse::Append(PluginSearchResult {
-                        id: id as u32,
-                        name: script.name.clone(),
-                        description: script.description.clone(),
-                        icon: script
-                            .icon
-                            .as_ref()
-                            .map(|icon| IconSource::Name(icon.clone().into())),
-                        keywords: Some(script.keywords.clone()),
-                        ..Default::default()
-                    }),
-                )
+                out.send(PluginResponse::Append(PluginSearchResult {
+                    id: id as u32,
+                    name: script.name.clone(),
+                    description: script.description.clone(),
+                    icon: script
+                        .icon
+                        .as_ref()
+                        .map(|icon| IconSource::Name(icon.clone().into())),
+                    keywords: Some(script.keywords.clone()),
+                    dedupe_key: script.appid.clone(),
+                    ..Default::default()
+                }))
                 .await;
             }
         }
 
-        send(out, PluginResponse::Finished).await;
+        out.send(PluginResponse::Finished).await;
     }
 }
 
 #[derive(Debug, Default)]
 struct ScriptInfo {
+    /// ID of the application the script launches, if it stands in for one.
+    appid: Option<String>,
     interpreter: Option<String>,
     name: String,
     icon: Option<String>,
     path: PathBuf,
     keywords: Vec<String>,
     description: String,
+    /// The name, description, and keywords, folded for comparison with queries.
+    folded: Vec<String>,
+}
+
+impl ScriptInfo {
+    /// Folds the texts that queries are compared with, keeping their accents if asked.
+    fn fold(&mut self, keep_accents: bool) {
+        self.folded = std::iter::once(&self.name)
+            .chain(std::iter::once(&self.description))
+            .chain(&self.keywords)
+            .map(|text| normalize::fold_with(text, !keep_accents))
+            .collect();
+    }
 }
 
 async fn load_from(path: &Path, paths: &mut VecDeque<PathBuf>, tx: Sender<ScriptInfo>) {
@@ -195,6 +231,8 @@ async fn load_from(path: &Path, paths: &mut VecDeque<PathBuf>, tx: Sender<Script
                     } else if let Some(stripped) = line.strip_prefix("keywords:") {
                         info.keywords =
                             stripped.trim_start().split(' ').map(String::from).collect();
+                    } else if let Some(stripped) = line.strip_prefix("appid:") {
+                        info.appid = Some(stripped.trim_start().to_owned());
                     }
                 }
 
@@ -203,3 +241,38 @@ async fn load_from(path: &Path, paths: &mut VecDeque<PathBuf>, tx: Sender<Script
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn accents_are_kept_when_queries_ask() {
+        let (tx, rx) = flume::unbounded();
+        let mut app = App::new(Output::channel(tx));
+
+        let mut script = ScriptInfo {
+            name: String::from("Écran"),
+            ..Default::default()
+        };
+
+        script.fold(app.keep_accents);
+        app.scripts.push(script);
+
+        // Each search refolds the script if it changes whether accents are kept
+        for &(keep_accents, expected) in &[(false, true), (true, false), (false, true)] {
+            let query = Query {
+                keep_accents,
+                ..Query::from("ecran")
+            };
+
+            app.search(&query).await;
+
+            let found = rx
+                .drain()
+                .any(|response| matches!(response, PluginResponse::Append(_)));
+
+            assert_eq!(expected, found, "keep_accents: {}", keep_accents);
+        }
+    }
+}
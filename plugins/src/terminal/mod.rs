@@ -1,36 +1,39 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright © 2021 System76
 
+use crate::{Output, Requests};
 use futures::prelude::*;
 use pop_launcher::*;
 use std::path::PathBuf;
 
+#[derive(Default)]
 pub struct App {
     last_query: Option<String>,
-    out: tokio::io::Stdout,
+    out: Output,
     shell_only: bool,
 }
 
-impl Default for App {
-    fn default() -> Self {
-        Self {
-            last_query: None,
-            out: async_stdout(),
-            shell_only: false,
-        }
-    }
+pub async fn main() {
+    run(crate::stdin_requests(), Output::default()).await;
 }
 
-pub async fn main() {
-    let mut requests = json_input_stream(async_stdin());
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
 
-    let mut app = App::default();
+    let mut app = App {
+        out,
+        ..App::default()
+    };
 
     while let Some(result) = requests.next().await {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
-                Request::Search(query) => app.search(query).await,
+                Request::Search(query) => {
+                    app.out.searching(&query);
+                    app.search(query).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -50,7 +53,7 @@ impl App {
 
         use fork::{daemon, Fork};
 
-        crate::send(&mut self.out, PluginResponse::Close).await;
+        self.out.send(PluginResponse::Close).await;
 
         if let Ok(Fork::Child) = daemon(true, false) {
             use std::os::unix::process::CommandExt;
@@ -78,40 +81,26 @@ impl App {
         std::process::exit(0);
     }
 
-    async fn search(&mut self, query: String) {
+    async fn search(&mut self, query: Query) {
         self.splice_input(&query).await;
-        crate::send(&mut self.out, PluginResponse::Finished).await;
+        self.out.send(PluginResponse::Finished).await;
     }
 
-    async fn splice_input(&mut self, mut query: &str) {
-        if let Some(q) = query.strip_prefix(':') {
-            self.shell_only = true;
-            query = q.trim();
-            self.last_query = Some(query.to_owned());
-        } else {
-            self.shell_only = false;
-
-            let query = if let Some(query) = query.strip_prefix("t:") {
-                query.trim()
-            } else if let Some(pos) = query.find(' ') {
-                query[pos + 1..].trim()
-            } else {
-                return;
-            };
+    async fn splice_input(&mut self, query: &Query) {
+        // Commands triggered by `:` run in the background without a terminal
+        self.shell_only = query.trigger.as_deref() == Some(":");
 
-            self.last_query = Some(query.to_owned());
-        }
+        let query = query.text.trim();
+        self.last_query = Some(query.to_owned());
 
-        crate::send(
-            &mut self.out,
-            PluginResponse::Append(PluginSearchResult {
+        self.out
+            .send(PluginResponse::Append(PluginSearchResult {
                 id: 0,
                 name: query.to_owned(),
                 description: String::from("run command in terminal"),
                 ..Default::default()
-            }),
-        )
-        .await;
+            }))
+            .await;
     }
 }
 
@@ -7,6 +7,8 @@ use std::collections::HashMap;
 
 #[derive(Default, Clone)]
 pub struct Config {
+    /// Why config files could not be read, to be reported to the user
+    pub failures: Vec<String>,
     matches: HashMap<String, u32>,
     queries: Slab<Vec<Definition>>,
 }
@@ -56,6 +58,9 @@ pub fn load() -> Config {
             Ok(string) => string,
             Err(why) => {
                 tracing::error!("failed to read config: {}", why);
+                config
+                    .failures
+                    .push(format!("failed to read {}: {}", path.display(), why));
                 continue;
             }
         };
@@ -64,6 +69,9 @@ pub fn load() -> Config {
             Ok(raw) => config.append(raw),
             Err(why) => {
                 tracing::error!("failed to deserialize config: {}", why);
+                config
+                    .failures
+                    .push(format!("failed to parse {}: {}", path.display(), why));
             }
         }
     }
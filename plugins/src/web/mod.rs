@@ -10,6 +10,7 @@ use futures::StreamExt;
 use reqwest::Client;
 use url::Url;
 
+use crate::{Output, Requests};
 use pop_launcher::*;
 
 pub use config::{load, Config, Definition};
@@ -17,15 +18,26 @@ use regex::Regex;
 
 mod config;
 pub async fn main() {
-    let mut app = App::default();
+    run(crate::stdin_requests(), Output::default()).await;
+}
+
+/// Serves requests from the launcher service until it is asked to exit.
+pub async fn run(mut requests: Requests, mut out: Output) {
+    out.hello().await;
 
-    let mut requests = json_input_stream(async_stdin());
+    let mut app = App {
+        out,
+        ..App::default()
+    };
 
     while let Some(result) = requests.next().await {
         match result {
             Ok(request) => match request {
                 Request::Activate(id) => app.activate(id).await,
-                Request::Search(query) => app.search(query).await,
+                Request::Search(query) => {
+                    app.out.searching(&query);
+                    app.search(query.text).await
+                }
                 Request::Exit => break,
                 _ => (),
             },
@@ -37,7 +49,7 @@ pub async fn main() {
 pub struct App {
     config: Config,
     queries: Vec<String>,
-    out: tokio::io::Stdout,
+    out: Output,
     client: Client,
     cache: PathBuf,
 }
@@ -63,7 +75,7 @@ impl Default for App {
         Self {
             config: config::load(),
             queries: Vec::new(),
-            out: async_stdout(),
+            out: Output::default(),
             client: Client::builder()
                 .timeout(Duration::from_secs(1))
                 .build()
@@ -80,11 +92,22 @@ impl App {
             crate::xdg_open(query);
         }
 
-        crate::send(&mut self.out, PluginResponse::Close).await;
+        self.out.send(PluginResponse::Close).await;
     }
 
     pub async fn search(&mut self, query: String) {
         self.queries.clear();
+
+        // Reported once, as every query is searched
+        for message in self.config.failures.drain(..) {
+            self.out
+                .send(PluginResponse::Error {
+                    kind: ErrorKind::Failed,
+                    message,
+                })
+                .await;
+        }
+
         if let Some(word) = query.split_ascii_whitespace().next() {
             if let Some(defs) = self.config.get(word) {
                 for (id, def) in defs.iter().enumerate() {
@@ -93,24 +116,22 @@ impl App {
                     let encoded = build_query(def, query);
                     let icon = self.get_favicon(def).await;
 
-                    crate::send(
-                        &mut self.out,
-                        PluginResponse::Append(PluginSearchResult {
+                    self.out
+                        .send(PluginResponse::Append(PluginSearchResult {
                             id: id as u32,
                             name: [&def.name, ": ", query].concat(),
                             description: encoded.clone(),
                             icon,
                             ..Default::default()
-                        }),
-                    )
-                    .await;
+                        }))
+                        .await;
 
                     self.queries.push(encoded);
                 }
             }
         }
 
-        crate::send(&mut self.out, PluginResponse::Finished).await;
+        self.out.send(PluginResponse::Finished).await;
     }
 }
 
@@ -0,0 +1,92 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Words which expand into longer queries when typed at the start of a query.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// Expansions of aliases, by the alias which expands into them.
+pub type Aliases = BTreeMap<String, String>;
+
+/// Replaces the alias which the text begins with by its expansion, if it begins
+/// with an alias.
+pub fn expand<'a>(aliases: &Aliases, text: &'a str) -> Cow<'a, str> {
+    let (word, rest) = split(text);
+
+    match aliases.get(word) {
+        Some(expansion) => Cow::Owned([expansion, rest].concat()),
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// Aliases to suggest for the text typed, with the text that each would expand it to.
+///
+/// While the first word is being typed, every alias which begins with it is
+/// suggested. Afterwards, only the alias being expanded is.
+pub fn hints<'a>(aliases: &'a Aliases, text: &str) -> Vec<(&'a str, String)> {
+    let (word, rest) = split(text);
+
+    if word.is_empty() {
+        return Vec::new();
+    }
+
+    aliases
+        .iter()
+        .filter(|(alias, _)| {
+            if rest.is_empty() {
+                alias.starts_with(word)
+            } else {
+                *alias == word
+            }
+        })
+        .map(|(alias, expansion)| (alias.as_str(), [expansion, rest].concat()))
+        .collect()
+}
+
+/// Splits the first word from the text, leaving the whitespace which follows it.
+fn split(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+
+    match text.find(char::is_whitespace) {
+        Some(end) => text.split_at(end),
+        None => (text, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_leading_alias() {
+        let aliases: Aliases = [
+            ("gh", "ddg site:github.com"),
+            ("ghr", "ddg site:github.com/rust-lang"),
+            ("ll", "~/Projects/"),
+        ]
+        .iter()
+        .map(|(alias, expansion)| (alias.to_string(), expansion.to_string()))
+        .collect();
+
+        assert_eq!(
+            "ddg site:github.com launcher",
+            expand(&aliases, "gh launcher")
+        );
+        assert_eq!("~/Projects/", expand(&aliases, "ll"));
+        assert_eq!("ghost", expand(&aliases, "ghost"));
+        assert_eq!("run gh", expand(&aliases, "run gh"));
+
+        let aliases_of = |text| {
+            hints(&aliases, text)
+                .into_iter()
+                .map(|(alias, _)| alias)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["gh", "ghr"], aliases_of("g"));
+        assert_eq!(vec!["gh"], aliases_of("gh launcher"));
+        assert!(aliases_of("").is_empty());
+        assert!(aliases_of("ghost").is_empty());
+    }
+}
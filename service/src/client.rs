@@ -4,7 +4,8 @@
 use futures::{Stream, StreamExt};
 use pop_launcher::{Request, Response};
 use std::io;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::unix::OwnedWriteHalf;
 use tokio::process;
 use tokio_stream::wrappers::LinesStream;
 
@@ -30,34 +31,60 @@ impl IpcClient {
             .take()
             .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "failed to find child stdout"))?;
 
-        let responses = LinesStream::new(tokio::io::BufReader::new(stdout).lines()).filter_map(
-            |result| async move {
-                if let Ok(line) = result {
-                    if let Ok(event) = serde_json::from_str::<Response>(&line) {
-                        return Some(event);
-                    }
-                }
-
-                None
-            },
-        );
-
         let client = Self { child, stdin };
 
-        Ok((client, responses))
+        Ok((client, responses(stdout)))
     }
 
     pub async fn send(&mut self, request: Request) -> io::Result<()> {
-        let mut request_json = serde_json::to_string(&request)
-            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        send(&mut self.stdin, request).await
+    }
+
+    pub async fn exit(mut self) {
+        let _ = self.send(Request::Exit).await;
+        let _ = self.child.wait().await;
+    }
+}
+
+/// A connection to a launcher service running with `--daemon`.
+pub struct DaemonClient {
+    pub stream: OwnedWriteHalf,
+}
 
-        request_json.push('\n');
+impl DaemonClient {
+    pub async fn connect() -> io::Result<(Self, impl Stream<Item = Response>)> {
+        let (reader, stream) = crate::daemon::connect().await?.into_split();
 
-        self.stdin.write_all(request_json.as_bytes()).await
+        Ok((Self { stream }, responses(reader)))
     }
 
+    pub async fn send(&mut self, request: Request) -> io::Result<()> {
+        send(&mut self.stream, request).await
+    }
+
+    /// Ends the connection, leaving the daemon running.
     pub async fn exit(mut self) {
         let _ = self.send(Request::Exit).await;
-        let _ = self.child.wait().await;
     }
 }
+
+async fn send(input: &mut (impl AsyncWrite + Unpin), request: Request) -> io::Result<()> {
+    let mut request_json = serde_json::to_string(&request)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    request_json.push('\n');
+
+    input.write_all(request_json.as_bytes()).await
+}
+
+fn responses(output: impl AsyncRead + Unpin) -> impl Stream<Item = Response> {
+    LinesStream::new(tokio::io::BufReader::new(output).lines()).filter_map(|result| async move {
+        if let Ok(line) = result {
+            if let Ok(event) = serde_json::from_str::<Response>(&line) {
+                return Some(event);
+            }
+        }
+
+        None
+    })
+}
@@ -0,0 +1,253 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Serves frontends over a Unix socket, so that plugins are kept running between them.
+
+use crate::{Event, Service};
+use flume::Sender;
+use futures::StreamExt;
+use pop_launcher::{json_input_stream, Request, Response};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{UnixListener, UnixStream};
+
+/// Location of the socket which the daemon listens on.
+pub fn socket_path() -> PathBuf {
+    socket_dir().join("pop-launcher.sock")
+}
+
+/// The user's runtime directory, or else a directory of their own in the
+/// temporary directory, which is shared with other users.
+fn socket_dir() -> PathBuf {
+    dirs::runtime_dir().unwrap_or_else(|| {
+        let uid = unsafe { libc::getuid() };
+        std::env::temp_dir().join(format!("pop-launcher-{}", uid))
+    })
+}
+
+/// Creates the directory of the socket if it is missing, and ensures that no
+/// other user could have put a socket of their own in its place.
+fn create_socket_dir() -> io::Result<()> {
+    let dir = socket_dir();
+
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(why) if why.kind() != io::ErrorKind::AlreadyExists => return Err(why),
+        _ => (),
+    }
+
+    check_private(&dir)
+}
+
+/// Fails unless the path is a directory owned by the user, which no one else may access.
+pub(crate) fn check_private(dir: &Path) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(dir)?;
+    let uid = unsafe { libc::getuid() };
+
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory private to the user", dir.display()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that the socket is the user's own before connecting to it.
+pub(crate) async fn connect() -> io::Result<UnixStream> {
+    check_private(&socket_dir())?;
+    UnixStream::connect(socket_path()).await
+}
+
+/// Binds to the path, in place of any socket left behind by a daemon which did
+/// not exit cleanly.
+async fn bind(path: &Path) -> io::Result<UnixListener> {
+    if UnixStream::connect(path).await.is_err() {
+        let _ = std::fs::remove_file(path);
+    }
+
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+pub async fn main(builtins: &'static [(&'static str, crate::Builtin)]) {
+    if let Err(why) = create_socket_dir() {
+        tracing::error!("refusing to listen: {}", why);
+        return;
+    }
+
+    let path = socket_path();
+
+    let listener = match bind(&path).await {
+        Ok(listener) => listener,
+        Err(why) => {
+            tracing::error!("failed to bind to {}: {}", path.display(), why);
+            return;
+        }
+    };
+
+    tracing::info!("listening on {}", path.display());
+
+    let (service, recording) =
+        crate::recording::attach(Service::new(futures::sink::drain()).builtins(builtins));
+
+    futures::future::join(service.serve(listener), recording).await;
+}
+
+/// Accepts connections from frontends, and serves each in the background.
+pub(crate) async fn listen(listener: UnixListener, tx: Sender<Event>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(connection(stream, tx.clone()));
+            }
+            Err(why) => tracing::error!("failed to accept connection: {}", why),
+        }
+    }
+}
+
+/// Forwards requests from a frontend to its session, and responses back to the frontend.
+async fn connection(stream: UnixStream, tx: Sender<Event>) {
+    let (reader, mut writer) = stream.into_split();
+    // The service disconnects a frontend whose responses fill this, rather than waiting on it
+    let (output_tx, output_rx) = flume::bounded::<Response>(64);
+    let (session_tx, session_rx) = async_oneshot::oneshot();
+
+    if tx
+        .send_async(Event::Connect(output_tx, session_tx))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let session = match session_rx.await {
+        Ok(session) => session,
+        Err(_) => return,
+    };
+
+    tracing::debug!("frontend connected to session {}", session);
+
+    let tx_ = tx.clone();
+    let requests = async move {
+        let mut requests = json_input_stream(reader);
+
+        while let Some(result) = requests.next().await {
+            match result {
+                Ok(request) => {
+                    let exit = matches!(request, Request::Exit);
+
+                    let _ = tx_.send_async(Event::Request(session, request)).await;
+
+                    if exit {
+                        break;
+                    }
+                }
+                Err(why) => tracing::error!("malformed JSON input: {}", why),
+            }
+        }
+    };
+
+    let responses = async move {
+        while let Ok(response) = output_rx.recv_async().await {
+            if let Ok(mut vec) = serde_json::to_vec(&response) {
+                vec.push(b'\n');
+                if writer.write_all(&vec).await.is_err() {
+                    break;
+                }
+            }
+        }
+    };
+
+    futures::pin_mut!(requests);
+    futures::pin_mut!(responses);
+
+    let _ = futures::future::select(requests, responses).await;
+
+    let _ = tx.send_async(Event::Disconnect(session)).await;
+
+    tracing::debug!("frontend disconnected from session {}", session);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn socket_dir_must_be_private() {
+        let dir = std::env::temp_dir().join(format!("pop-launcher-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::DirBuilder::new().mode(0o700).create(&dir).unwrap();
+
+        assert!(check_private(&dir).is_ok());
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(check_private(&dir).is_err());
+
+        let link = dir.with_extension("link");
+        let _ = std::fs::remove_file(&link);
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert!(check_private(&link).is_err());
+
+        let _ = std::fs::remove_file(&link);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn serves_frontends_until_they_exit() {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        let dir = std::env::temp_dir().join(format!("pop-launcher-sock-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::DirBuilder::new().mode(0o700).create(&dir).unwrap();
+        let path = dir.join("pop-launcher.sock");
+
+        // A socket left behind by a daemon which did not exit cleanly is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let listener = bind(&path).await.unwrap();
+
+        let (tx, rx) = flume::bounded(1);
+        tokio::spawn(listen(listener, tx));
+
+        let stream = UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+
+        let output = match rx.recv_async().await.unwrap() {
+            Event::Connect(output, mut session) => {
+                session.send(7).unwrap();
+                output
+            }
+            _ => panic!("expected the frontend to connect"),
+        };
+
+        writer.write_all(b"{\"Search\":\"ls\"}\n").await.unwrap();
+        assert!(matches!(
+            rx.recv_async().await.unwrap(),
+            Event::Request(7, Request::Search(query)) if query.text == "ls"
+        ));
+
+        output.send_async(Response::Close).await.unwrap();
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line).await.unwrap();
+        assert_eq!("\"Close\"\n", line);
+
+        writer.write_all(b"\"Exit\"\n").await.unwrap();
+        assert!(matches!(
+            rx.recv_async().await.unwrap(),
+            Event::Request(7, Request::Exit)
+        ));
+        assert!(matches!(
+            rx.recv_async().await.unwrap(),
+            Event::Disconnect(7)
+        ));
+
+        // But not one which a daemon is still listening on
+        assert!(bind(&path).await.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
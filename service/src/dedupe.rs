@@ -0,0 +1,94 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{PluginKey, PluginPriority, PluginSearchResult};
+use std::collections::HashMap;
+
+/// Collapses results which share a dedupe key, such as a file found by several plugins.
+///
+/// The result of the plugin with the highest priority is kept, or the first one found
+/// among plugins of equal priority, and the keywords of the others are merged into it.
+pub fn dedupe(
+    results: &mut Vec<(PluginKey, PluginSearchResult)>,
+    priority: impl Fn(PluginKey) -> PluginPriority,
+) {
+    let mut kept: HashMap<String, usize> = HashMap::new();
+    let mut unique = Vec::with_capacity(results.len());
+
+    for (plugin, result) in results.drain(..) {
+        let key = match result.dedupe_key.as_ref() {
+            Some(key) => key,
+            None => {
+                unique.push((plugin, result));
+                continue;
+            }
+        };
+
+        let pos = match kept.get(key) {
+            Some(&pos) => pos,
+            None => {
+                kept.insert(key.clone(), unique.len());
+                unique.push((plugin, result));
+                continue;
+            }
+        };
+
+        let existing = &mut unique[pos];
+
+        let discarded = if priority(plugin) < priority(existing.0) {
+            std::mem::replace(existing, (plugin, result)).1
+        } else {
+            result
+        };
+
+        if let Some(keywords) = discarded.keywords {
+            let merged = existing.1.keywords.get_or_insert_with(Vec::new);
+            for keyword in keywords {
+                if !merged.contains(&keyword) {
+                    merged.push(keyword);
+                }
+            }
+        }
+    }
+
+    *results = unique;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, key: Option<&str>, keywords: &[&str]) -> PluginSearchResult {
+        PluginSearchResult {
+            name: name.to_owned(),
+            dedupe_key: key.map(String::from),
+            keywords: Some(keywords.iter().map(|x| String::from(*x)).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keeps_highest_priority_and_merges_keywords() {
+        let priority = |plugin| match plugin {
+            0 => PluginPriority::Low,
+            _ => PluginPriority::High,
+        };
+
+        let mut results = vec![
+            (0, result("notes.txt", Some("file:///notes"), &["recent"])),
+            (0, result("todo.txt", None, &[])),
+            (1, result("notes", Some("file:///notes"), &["text"])),
+            (1, result("other", Some("file:///other.txt"), &[])),
+        ];
+
+        dedupe(&mut results, priority);
+
+        let names: Vec<&str> = results.iter().map(|(_, r)| r.name.as_str()).collect();
+        assert_eq!(vec!["notes", "todo.txt", "other"], names);
+
+        assert_eq!(
+            Some(vec![String::from("text"), String::from("recent")]),
+            results[0].1.keywords
+        );
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::persist::Persist;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Seconds until the recency of an activation is worth half as much.
+const HALF_LIFE: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
+/// Entries with a decayed frequency below this value are forgotten on save.
+const PRUNE_THRESHOLD: f64 = 0.01;
+
+/// How often an item was activated, and when it was last activated.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct Activation {
+    pub count: u32,
+    pub last_used: u64,
+}
+
+impl Activation {
+    /// Frequency of use, decayed by the time elapsed since its last use.
+    pub fn decayed(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_used) as f64;
+        f64::from(self.count) * (-age * std::f64::consts::LN_2 / HALF_LIFE).exp()
+    }
+}
+
+/// Activations of results from plugins which have `history` enabled, keyed by
+/// plugin name and then by the name of the result.
+#[derive(Debug, Default)]
+pub struct History {
+    path: Option<PathBuf>,
+    persist: Persist,
+    plugins: HashMap<String, HashMap<String, Activation>>,
+}
+
+impl History {
+    /// Loads the activation history from the XDG state directory.
+    pub fn load() -> Self {
+        let path = match history_path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+
+        let plugins = match std::fs::read_to_string(&path) {
+            Ok(string) => match ron::from_str(&string) {
+                Ok(plugins) => plugins,
+                Err(why) => {
+                    tracing::error!("malformed history at {}: {}", path.display(), why);
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path: Some(path),
+            persist: Persist::default(),
+            plugins,
+        }
+    }
+
+    /// Frecency of a result, between 0.0 and 1.0, with higher values being used most.
+    pub fn frecency(&self, plugin: &str, name: &str) -> f64 {
+        self.plugins
+            .get(plugin)
+            .and_then(|items| items.get(name))
+            .map_or(0.0, |activation| {
+                let decayed = activation.decayed(now());
+                decayed / (decayed + 1.0)
+            })
+    }
+
+    /// Records the activation of a result, and persists the history to disk.
+    pub fn record(&mut self, plugin: &str, name: &str) {
+        let activation = self
+            .plugins
+            .entry(plugin.to_owned())
+            .or_default()
+            .entry(name.to_owned())
+            .or_default();
+
+        activation.count += 1;
+        activation.last_used = now();
+
+        self.save();
+    }
+
+    fn save(&mut self) {
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return,
+        };
+
+        let now = now();
+
+        for items in self.plugins.values_mut() {
+            items.retain(|_, activation| activation.decayed(now) >= PRUNE_THRESHOLD);
+        }
+
+        self.plugins.retain(|_, items| !items.is_empty());
+
+        match ron::to_string(&self.plugins) {
+            Ok(string) => self.persist.write(path.clone(), string),
+            Err(why) => tracing::error!("failed to serialize history: {}", why),
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/state")))
+        .map(|dir| dir.join("pop-launcher/history.ron"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frecency_favors_frequent_and_recent() {
+        let now = now();
+        let mut history = History::default();
+
+        let items = history.plugins.entry("apps".into()).or_default();
+
+        items.insert(
+            "Firefox".into(),
+            Activation {
+                count: 20,
+                last_used: now,
+            },
+        );
+        items.insert(
+            "Files".into(),
+            Activation {
+                count: 1,
+                last_used: now,
+            },
+        );
+        items.insert(
+            "Fractal".into(),
+            Activation {
+                count: 20,
+                last_used: now - 60 * 24 * 60 * 60,
+            },
+        );
+
+        let firefox = history.frecency("apps", "Firefox");
+        let files = history.frecency("apps", "Files");
+        let fractal = history.frecency("apps", "Fractal");
+
+        assert!(firefox > files);
+        assert!(files > fractal);
+        assert_eq!(0.0, history.frecency("apps", "Unknown"));
+        assert_eq!(0.0, history.frecency("web", "Firefox"));
+    }
+}
@@ -1,40 +1,91 @@
 // Copyright 2021 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
+mod alias;
 mod client;
+pub mod daemon;
+mod dedupe;
+mod history;
+mod persist;
 mod plugins;
+mod recall;
+pub mod recording;
+pub mod scorer;
+mod session;
+mod settings;
+pub mod stats;
 
 pub use client::*;
+pub use plugins::builtin::Builtin;
 pub use plugins::config;
 pub use plugins::external::load;
 
+use crate::history::History;
 use crate::plugins::*;
+use crate::recall::Recall;
+use crate::recording::{Entry, Recorder};
+use crate::scorer::{HeuristicScorer, Scorer};
+use crate::session::{Recalling, Session, SessionKey};
+use crate::settings::Settings;
 use flume::{Receiver, Sender};
 use futures::{future, SinkExt, Stream, StreamExt};
 use pop_launcher::*;
 use regex::Regex;
 use slab::Slab;
 use std::{
-    collections::{HashMap, HashSet},
+    borrow::Cow,
+    collections::HashMap,
     io::{self, Write},
+    path::PathBuf,
+    time::Duration,
 };
+use tokio::{net::UnixListener, time::Instant};
 
 pub type PluginKey = usize;
 
+/// Identifies the plugin which sent an event. The key of an unloaded plugin may be
+/// reused by a plugin loaded after it, which is told apart by its generation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PluginId {
+    pub key: PluginKey,
+    pub generation: u64,
+}
+
+/// Maximum weight that the frecency of a result may add to its similarity weight.
+const FRECENCY_WEIGHT: f64 = 0.5;
+
+/// Milliseconds to wait for a plugin to finish searching, unless its config defines a timeout.
+const SEARCH_TIMEOUT: u64 = 5000;
+
 pub enum Event {
-    Request(Request),
-    Response((PluginKey, PluginResponse)),
-    PluginExit(PluginKey),
+    Request(SessionKey, Request),
+    Response((PluginId, PluginResponse)),
+    /// A plugin stopped before it was asked to, leaving its oldest searches unfinished.
+    PluginExit(PluginId, usize),
     Help(async_oneshot::Sender<Slab<PluginHelp>>),
+    /// A plugin's process exited with an error, or could not be launched.
+    PluginCrashed(PluginId),
+    /// A plugin crashed too often, and has been given up on.
+    PluginFailed(PluginId, String),
+    /// Plugins were installed, removed, or had their config changed.
+    PluginsChanged,
+    /// A frontend connected, and is told the key of its session.
+    Connect(Sender<Response>, async_oneshot::Sender<SessionKey>),
+    /// A frontend disconnected.
+    Disconnect(SessionKey),
 }
 
 pub struct PluginHelp {
     pub name: String,
     pub description: String,
     pub help: Option<String>,
+    /// Why the plugin was disabled, if it was.
+    pub failure: Option<String>,
+    /// Pattern which queries must match to be sent to the plugin.
+    pub regex: Option<String>,
 }
 
-pub async fn main() {
+pub async fn main(builtins: &'static [(&'static str, Builtin)]) {
     // Listens for a stream of requests from stdin.
     let input_stream = json_input_stream(tokio::io::stdin()).filter_map(|result| {
         future::ready(match result {
@@ -48,8 +99,11 @@ pub async fn main() {
 
     let (output_tx, output_rx) = flume::bounded(16);
 
+    let (service, recording) =
+        recording::attach(Service::new(output_tx.into_sink()).builtins(builtins));
+
     // Service will operate for as long as it is being awaited
-    let service = Service::new(output_tx.into_sink()).exec(input_stream);
+    let service = service.exec(input_stream);
 
     // Responses from the service will be streamed to stdout
     let responder = async move {
@@ -61,168 +115,566 @@ pub async fn main() {
         }
     };
 
-    futures::future::join(service, responder).await;
+    futures::future::join3(service, responder, recording).await;
 }
 
 pub struct Service<O> {
-    active_search: Vec<(PluginKey, PluginSearchResult)>,
-    associated_list: HashMap<Indice, Indice>,
-    awaiting_results: HashSet<PluginKey>,
-    last_query: String,
-    no_sort: bool,
+    /// Plugins which may run within the service, by the command which runs them
+    builtins: &'static [(&'static str, Builtin)],
+    /// Generation of the latest plugin to be loaded
+    generation: u64,
+    history: History,
+    next_session: SessionKey,
     output: O,
     plugins: Slab<PluginConnector>,
-    search_scheduled: bool,
+    /// Queries which led to activations, which frontends may recall
+    recall: Recall,
+    /// Records the messages passing through the service, if it is being recorded
+    recorder: Option<Recorder>,
+    scorer: Box<dyn Scorer>,
+    /// ID of the latest search sent to plugins by any session
+    search_id: QueryId,
+    /// Sender for events to the service, which newly-loaded plugins are given
+    service_tx: Option<Sender<Event>>,
+    sessions: HashMap<SessionKey, Session>,
+    settings: Settings,
 }
 
 impl<O: futures::Sink<Response> + Unpin> Service<O> {
     pub fn new(output: O) -> Self {
         Self {
-            active_search: Vec::new(),
-            associated_list: HashMap::new(),
-            awaiting_results: HashSet::new(),
-            last_query: String::new(),
+            builtins: &[],
+            generation: 0,
+            history: History::default(),
+            next_session: 0,
             output,
-            no_sort: false,
             plugins: Slab::new(),
-            search_scheduled: false,
+            recall: Recall::default(),
+            recorder: None,
+            scorer: Box::<HeuristicScorer>::default(),
+            search_id: 0,
+            service_tx: None,
+            sessions: HashMap::new(),
+            settings: Settings::default(),
         }
     }
 
+    /// Runs the plugins given within the service, when they are enabled to, rather
+    /// than running the multicall binary in a process of its own.
+    pub fn builtins(mut self, builtins: &'static [(&'static str, Builtin)]) -> Self {
+        self.builtins = builtins;
+        self
+    }
+
+    /// Records the messages passing through the service with the recorder given.
+    pub fn recorded(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Serves a single frontend, whose responses are sent to the service's output.
     pub async fn exec(mut self, input: impl Stream<Item = Request>) {
         let (service_tx, service_rx) = flume::bounded(1);
-        let stream = plugins::external::load::from_paths();
 
-        futures::pin_mut!(stream);
+        self.load(&service_tx).await;
 
-        while let Some((exec, config, regex)) = stream.next().await {
-            tracing::info!("found plugin \"{}\"", exec.display());
-            if self
-                .plugins
-                .iter()
-                .any(|(_, p)| p.config.name == config.name)
-            {
-                tracing::info!("ignoring plugin");
-                continue;
-            }
+        let session = self.connect(None);
 
-            let name = String::from(config.name.as_ref());
+        let f1 = request_handler(session, input, service_tx);
+        let f2 = self.response_handler(service_rx);
 
-            self.register_plugin(service_tx.clone(), config, regex, move |id, tx| {
-                ExternalPlugin::new(id, name.clone(), exec.clone(), Vec::new(), tx)
-            });
-        }
+        futures::pin_mut!(f1);
+        futures::pin_mut!(f2);
 
-        self.register_plugin(
-            service_tx.clone(),
-            plugins::help::CONFIG,
-            Some(Regex::new(plugins::help::REGEX.as_ref()).expect("failed to compile help regex")),
-            HelpPlugin::new,
-        );
+        let _ = futures::future::select(f1, f2).await.factor_first().0;
+    }
 
-        let f1 = request_handler(input, service_tx);
+    /// Serves every frontend which connects to the listener, until the service is killed.
+    pub async fn serve(mut self, listener: UnixListener) {
+        let (service_tx, service_rx) = flume::bounded(1);
+
+        self.load(&service_tx).await;
+
+        let f1 = daemon::listen(listener, service_tx);
         let f2 = self.response_handler(service_rx);
 
         futures::pin_mut!(f1);
         futures::pin_mut!(f2);
 
-        let _ = futures::future::select(f1, f2).await.factor_first().0;
+        let _ = futures::future::select(f1, f2).await;
+    }
+
+    async fn load(&mut self, service_tx: &Sender<Event>) {
+        self.history = History::load();
+        self.recall = Recall::load();
+        self.service_tx = Some(service_tx.clone());
+
+        self.reload_plugins().await;
+
+        tokio::spawn(plugins::external::watch::watch(service_tx.clone()));
     }
 
     async fn response_handler(&mut self, service_rx: Receiver<Event>) {
-        while let Ok(event) = service_rx.recv_async().await {
-            match event {
-                Event::Request(request) => {
-                    match request {
-                        Request::Search(query) => self.search(query).await,
-                        Request::Interrupt => self.interrupt().await,
-                        Request::Activate(id) => self.activate(id).await,
-                        Request::ActivateContext { id, context } => {
-                            self.activate_context(id, context).await
+        loop {
+            let event = match self.deadline() {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, service_rx.recv_async()).await {
+                        Ok(event) => event,
+                        Err(_) => {
+                            self.deadline_reached().await;
+                            continue;
                         }
-                        Request::Complete(id) => self.complete(id).await,
-                        Request::Context(id) => self.context(id).await,
-                        Request::Quit(id) => self.quit(id).await,
-
-                        // When requested to exit, the service will forward that
-                        // request to all of its plugins before exiting itself
-                        Request::Exit => {
-                            for (_key, plugin) in self.plugins.iter_mut() {
-                                let tx = plugin.sender_exec();
-                                let _ = tx.send_async(Request::Exit).await;
-                            }
-
-                            break;
+                    }
+                }
+                None => service_rx.recv_async().await,
+            };
+
+            let event = match event {
+                Ok(event) => event,
+                Err(_) => break,
+            };
+
+            if !self.handle(event).await {
+                break;
+            }
+        }
+    }
+
+    /// The earliest instant at which the service must act without an event.
+    fn deadline(&self) -> Option<Instant> {
+        self.sessions
+            .values()
+            .filter_map(Session::deadline)
+            .chain(self.plugins.iter().filter_map(|(_, p)| p.idle_deadline()))
+            .min()
+    }
+
+    /// Acts on the searches and plugins whose deadlines have passed.
+    async fn deadline_reached(&mut self) {
+        self.deadline_expired().await;
+        self.stop_idle_plugins().await;
+    }
+
+    /// Handles an event, and returns `false` if the service should exit.
+    async fn handle(&mut self, event: Event) -> bool {
+        match event {
+            Event::Request(session, request) => {
+                self.record(|| Entry::Request {
+                    session,
+                    request: request.clone(),
+                });
+
+                match request {
+                    Request::Search(query) => self.search(session, query).await,
+                    Request::Interrupt => self.interrupt(session).await,
+                    Request::Activate(id) => self.activate(session, id).await,
+                    Request::ActivateContext { id, context } => {
+                        self.activate_context(session, id, context).await
+                    }
+                    Request::Complete(id) => self.complete(session, id).await,
+                    Request::Context(id) => self.context(session, id).await,
+                    Request::Quit(id) => self.quit(session, id).await,
+                    Request::Hello(capabilities) => self.hello(session, capabilities).await,
+                    Request::History { direction } => self.recall_query(session, direction).await,
+                    Request::Stats => self.stats(session).await,
+                    Request::Page { offset, limit } => self.page(session, offset, limit).await,
+
+                    // When requested to exit, the service will forward that
+                    // request to all of its plugins before exiting itself.
+                    // Frontends connected to a daemon are merely disconnected.
+                    Request::Exit => {
+                        let daemon_session = self
+                            .sessions
+                            .get(&session)
+                            .is_some_and(|session| session.output.is_some());
+
+                        if daemon_session {
+                            self.disconnect(session);
+                            return true;
                         }
+
+                        for (_key, plugin) in self.plugins.iter_mut() {
+                            let tx = plugin.sender_exec();
+                            let _ = tx.send_async(Request::Exit).await;
+                        }
+
+                        return false;
                     }
                 }
+            }
+
+            Event::Response((id, response)) => {
+                let plugin = match self.loaded(id) {
+                    Some(plugin) => plugin,
+                    None => return true,
+                };
+
+                self.record(|| Entry::Plugin {
+                    plugin,
+                    response: response.clone(),
+                });
 
-                Event::Response((plugin, response)) => match response {
-                    PluginResponse::Append(item) => self.append(plugin, item),
-                    PluginResponse::Clear => self.clear(),
-                    PluginResponse::Close => self.close().await,
+                // Results are for the session whose search the plugin is answering,
+                // while other responses are for the session that made a request of it.
+                let (searcher, requester) = match self.plugins.get(plugin) {
+                    Some(conn) => {
+                        let searcher = conn.searches.front().copied().flatten();
+                        (searcher, conn.requester.or(searcher))
+                    }
+                    None => (None, None),
+                };
+
+                match response {
+                    PluginResponse::Append(item) => {
+                        if let Some(conn) = self.plugins.get_mut(plugin) {
+                            conn.appended();
+                        }
+
+                        if let Some(session) = searcher {
+                            self.append(session, plugin, item);
+                        }
+                    }
+                    PluginResponse::Clear => {
+                        if let Some(session) = searcher {
+                            self.clear(session);
+                        }
+                    }
+                    PluginResponse::Close => {
+                        if let Some(session) = requester {
+                            self.respond(session, Response::Close).await;
+                        }
+                    }
                     PluginResponse::Context { id, options } => {
-                        self.context_response(id, options).await
+                        if let Some(session) = requester {
+                            self.context_response(session, id, options).await;
+                        }
+                    }
+                    PluginResponse::Error { kind, message } => {
+                        self.plugin_error(plugin, searcher.or(requester), kind, message)
+                            .await;
+                    }
+                    PluginResponse::Fill(text) => {
+                        if let Some(session) = requester {
+                            self.respond(session, Response::Fill(text)).await;
+                        }
                     }
-                    PluginResponse::Fill(text) => self.fill(text).await,
                     PluginResponse::Finished => self.finished(plugin).await,
                     PluginResponse::DesktopEntry {
                         path,
                         gpu_preference,
                     } => {
-                        self.respond(Response::DesktopEntry {
-                            path,
-                            gpu_preference,
-                        })
-                        .await;
+                        if let Some(session) = requester {
+                            let response = Response::DesktopEntry {
+                                path,
+                                gpu_preference,
+                            };
+
+                            self.respond(session, response).await;
+                        }
                     }
 
+                    // External plugins are answered by their forwarder
+                    PluginResponse::Hello(_) => (),
+
                     // Report the plugin as finished and remove it from future polling
                     PluginResponse::Deactivate => {
                         self.finished(plugin).await;
-                        let _ = self.plugins.remove(plugin);
+                        let _ = self.plugins.try_remove(plugin);
                     }
-                },
+                }
+            }
 
-                // When a plugin has exited, the sender attached to the plugin will be dropped
-                Event::PluginExit(plugin_id) => {
-                    if let Some(plugin) = self.plugins.get_mut(plugin_id) {
-                        plugin.sender_drop();
-                    }
+            // A plugin which stopped before it was asked to will not finish its searches,
+            // and is relaunched when next sent a request
+            Event::PluginExit(id, searches) => {
+                let plugin_id = match self.loaded(id) {
+                    Some(plugin) => plugin,
+                    None => return true,
+                };
+
+                self.record(|| Entry::PluginExit {
+                    plugin: plugin_id,
+                    searches,
+                });
+
+                self.abandon_searches(plugin_id, searches).await;
+            }
+
+            Event::Help(mut sender) => {
+                let mut details = Slab::new();
+
+                for (_, plugin) in self.plugins.iter() {
+                    details.insert(plugin.details());
                 }
 
-                Event::Help(mut sender) => {
-                    let mut details = Slab::new();
+                let _ = sender.send(details);
+            }
+
+            Event::PluginCrashed(id) => {
+                let key = match self.loaded(id) {
+                    Some(key) => key,
+                    None => return true,
+                };
+
+                self.record(|| Entry::PluginCrashed { plugin: key });
+
+                if let Some(plugin) = self.plugins.get_mut(key) {
+                    plugin.stats.crashes += 1;
+                }
+            }
+
+            Event::PluginFailed(id, reason) => {
+                let key = match self.loaded(id) {
+                    Some(key) => key,
+                    None => return true,
+                };
+
+                self.record(|| Entry::PluginFailed {
+                    plugin: key,
+                    reason: reason.clone(),
+                });
+
+                if let Some(plugin) = self.plugins.get_mut(key) {
+                    plugin.fail(reason);
+                    self.report_failure(key).await;
+                    self.abandon_searches(key, usize::MAX).await;
+                }
+            }
+
+            Event::PluginsChanged => self.reload_plugins().await,
+
+            Event::Connect(output, mut sender) => {
+                let session = self.connect(Some(output));
+                let _ = sender.send(session);
+            }
+
+            Event::Disconnect(session) => self.disconnect(session),
+        }
+
+        true
+    }
+
+    /// The key of the plugin which sent an event, unless it has since been unloaded,
+    /// in which case its key may belong to another plugin.
+    fn loaded(&self, id: PluginId) -> Option<PluginKey> {
+        self.plugins
+            .get(id.key)
+            .filter(|plugin| plugin.generation == id.generation)
+            .map(|_| id.key)
+    }
+
+    /// The identity of the plugin which is loaded with the given key.
+    fn plugin_id(&self, key: PluginKey) -> PluginId {
+        PluginId {
+            key,
+            generation: self.plugins.get(key).map_or(0, |plugin| plugin.generation),
+        }
+    }
+
+    /// Loads the external plugins installed on the system, unloading those which have
+    /// since been removed, and reloading those whose config or executable changed.
+    ///
+    /// The help plugin is reloaded as well if the user's overrides for it changed.
+    async fn reload_plugins(&mut self) {
+        let service_tx = match self.service_tx.clone() {
+            Some(tx) => tx,
+            None => return,
+        };
+
+        // Overrides for plugins may have changed along with them
+        let previous = std::mem::replace(&mut self.settings, settings::load());
+        self.scorer = self
+            .settings
+            .scorer
+            .scorer(self.settings.accent_insensitive);
+        self.record(|| Entry::Settings(self.settings.clone()));
+
+        let mut found: Vec<(PathBuf, PluginConfig, Option<Regex>)> = Vec::new();
+
+        let stream = plugins::external::load::from_paths();
+
+        futures::pin_mut!(stream);
+
+        while let Some((exec, config, regex)) = stream.next().await {
+            tracing::info!("found plugin \"{}\"", exec.display());
+            if found.iter().any(|(_, c, _)| c.name == config.name) {
+                tracing::info!("ignoring plugin");
+                continue;
+            }
+
+            found.push((exec, config, regex));
+        }
+
+        found.retain(|(_, config, _)| {
+            let enabled = self.settings.enabled(&config.name);
+            if !enabled {
+                tracing::info!("plugin \"{}\" is disabled", config.name);
+            }
 
-                    for (_, plugin) in self.plugins.iter() {
-                        details.insert(plugin.details());
+            enabled
+        });
+
+        let help = Some(plugins::help::CONFIG).filter(|config| self.settings.enabled(&config.name));
+
+        let stale: Vec<PluginKey> = self
+            .plugins
+            .iter()
+            .filter(|(_, plugin)| {
+                let stale = match plugin.exec.as_ref() {
+                    Some(exec) => {
+                        !found
+                            .iter()
+                            .any(|(e, config, _)| e == exec && *config == plugin.loaded)
+                            || plugin.in_process != self.builtin(&plugin.config).is_some()
                     }
+                    None => help.as_ref() != Some(&plugin.loaded),
+                };
+
+                stale
+                    || self
+                        .settings
+                        .overrides_changed(&previous, &plugin.loaded.name)
+            })
+            .map(|(key, _)| key)
+            .collect();
+
+        let mut affected = Vec::new();
+
+        for key in stale {
+            affected.extend(self.unregister_plugin(key).await);
+        }
 
-                    let _ = sender.send(details);
+        for (exec, config, regex) in found {
+            if self
+                .plugins
+                .iter()
+                .any(|(_, p)| p.config.name == config.name)
+            {
+                continue;
+            }
+
+            let name = String::from(config.name.as_ref());
+            let builtin = self.builtin(&config);
+
+            let key = match builtin {
+                Some(main) => {
+                    tracing::info!("running plugin \"{}\" within the service", name);
+                    self.register_plugin(service_tx.clone(), config, regex, move |id, tx| {
+                        BuiltinPlugin::new(id, name.clone(), main, tx)
+                    })
+                }
+                None => {
+                    let bin = config.bin.clone().unwrap_or_default();
+                    self.register_plugin(service_tx.clone(), config, regex, move |id, tx| {
+                        ExternalPlugin::new(id, name.clone(), bin.clone(), tx)
+                    })
                 }
+            };
+
+            self.plugins[key].exec = Some(exec);
+            self.plugins[key].in_process = builtin.is_some();
+        }
+
+        if let Some(config) = help {
+            if !self
+                .plugins
+                .iter()
+                .any(|(_, p)| p.config.name == config.name)
+            {
+                let regex = Regex::new(plugins::help::REGEX.as_ref())
+                    .expect("failed to compile help regex");
+
+                self.register_plugin(service_tx, config, Some(regex), HelpPlugin::new);
             }
         }
+
+        for session in affected {
+            self.search_progressed(session).await;
+        }
+    }
+
+    /// The bundled plugin which runs the plugin within the service, if the plugin
+    /// is the multicall binary and the user has enabled it to run in-process.
+    fn builtin(&self, config: &PluginConfig) -> Option<Builtin> {
+        if !self.settings.in_process(&config.name) {
+            return None;
+        }
+
+        let command = plugins::builtin::command(config.bin.as_ref()?)?;
+
+        self.builtins
+            .iter()
+            .find(|(name, _)| *name == command)
+            .map(|&(_, main)| main)
     }
 
-    fn register_plugin<P: Plugin, I: Fn(usize, Sender<Event>) -> P + Send + Sync + 'static>(
+    /// Unloads a plugin, and discards its results from every session. Returns the
+    /// sessions which were awaiting results from the plugin.
+    async fn unregister_plugin(&mut self, key: PluginKey) -> Vec<SessionKey> {
+        if let Some(mut plugin) = self.plugins.try_remove(key) {
+            tracing::info!("unloading plugin \"{}\"", plugin.config.name);
+            self.record(|| Entry::Unloaded { plugin: key });
+
+            if let Some(sender) = plugin.sender.as_mut() {
+                let _ = sender.send_async(Request::Exit).await;
+            }
+        }
+
+        let mut affected = Vec::new();
+
+        for (session_key, session) in self.sessions.iter_mut() {
+            session.active_search.retain(|(plugin, _)| *plugin != key);
+
+            if session.isolated == Some(key) {
+                session.isolated = None;
+            }
+
+            if session.awaiting_results.remove(&key).is_some() {
+                affected.push(*session_key);
+            }
+        }
+
+        affected
+    }
+
+    fn register_plugin<P: Plugin, I: Fn(PluginId, Sender<Event>) -> P + Send + Sync + 'static>(
         &mut self,
         service_tx: Sender<Event>,
-        config: PluginConfig,
+        mut config: PluginConfig,
         regex: Option<regex::Regex>,
         init: I,
-    ) {
+    ) -> PluginKey {
+        let loaded = config.clone();
+        self.settings.apply(&mut config);
+
+        // The user may have replaced the pattern of a builtin plugin
+        let regex = config
+            .query
+            .regex
+            .as_ref()
+            .and_then(|expr| Regex::new(expr).ok())
+            .or(regex);
+
+        self.generation += 1;
+
         let entry = self.plugins.vacant_entry();
         let id = entry.key();
 
+        let plugin_id = PluginId {
+            key: id,
+            generation: self.generation,
+        };
+
         let init = std::sync::Arc::new(init);
 
         let isolate_with = config
             .query
             .isolate_with
             .as_ref()
-            .and_then(|expr| Regex::new(&*expr).ok());
+            .and_then(|expr| Regex::new(expr).ok());
 
-        entry.insert(PluginConnector::new(
+        let plugin = entry.insert(PluginConnector::new(
+            loaded,
             config,
             regex,
             isolate_with,
@@ -232,133 +684,609 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
                 let init = init.clone();
                 let service_tx = service_tx.clone();
                 tokio::spawn(async move {
-                    init(id, service_tx).run(request_rx).await;
+                    init(plugin_id, service_tx).run(request_rx).await;
                 });
 
                 request_tx
             }),
         ));
+
+        plugin.generation = plugin_id.generation;
+
+        self.record(|| Entry::Loaded {
+            plugin: id,
+            config: Box::new(self.plugins[id].config.clone()),
+            regex: self.plugins[id]
+                .regex
+                .as_ref()
+                .map(|r| r.as_str().to_owned()),
+        });
+
+        id
     }
 
-    async fn activate(&mut self, id: Indice) {
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            let _ = plugin
-                .sender_exec()
-                .send_async(Request::Activate(meta.id))
-                .await;
+    async fn activate(&mut self, session: SessionKey, id: Indice) {
+        if let Some(expansion) = self.hint(session, id) {
+            self.respond(session, Response::Fill(expansion)).await;
+            return;
+        }
+
+        let activation = self
+            .search_result(session, id as usize)
+            .filter(|(plugin, _)| plugin.config.history)
+            .map(|(plugin, meta)| (plugin.config.name.to_string(), meta.name.clone()));
+
+        let query = match self.sessions.get(&session) {
+            Some(session) => session.last_query.text.clone(),
+            None => return,
+        };
+
+        let prefix = self
+            .search_result(session, id as usize)
+            .map(|(plugin, _)| query_prefix(plugin, &query));
+
+        self.forward(session, id, Request::Activate).await;
+
+        if let Some((plugin, name)) = activation {
+            self.history.record(&plugin, &name);
+        }
+
+        if let Some(prefix) = prefix {
+            self.recall.record(&prefix, &query);
         }
     }
 
-    async fn activate_context(&mut self, id: Indice, context: Indice) {
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            let _ = plugin
-                .sender_exec()
-                .send_async(Request::ActivateContext {
-                    id: meta.id,
-                    context,
-                })
-                .await;
+    async fn activate_context(&mut self, session: SessionKey, id: Indice, context: Indice) {
+        self.forward(session, id, |id| Request::ActivateContext { id, context })
+            .await;
+    }
+
+    fn append(&mut self, session: SessionKey, plugin: PluginKey, append: PluginSearchResult) {
+        if let Some(session) = self.sessions.get_mut(&session) {
+            // Results from superseded queries are discarded
+            if append.query_id.is_some_and(|id| id != session.search_id) {
+                tracing::debug!("discarding result from superseded query");
+                return;
+            }
+
+            session.active_search.push((plugin, append));
         }
     }
 
-    fn append(&mut self, plugin: PluginKey, append: PluginSearchResult) {
-        self.active_search.push((plugin, append));
+    fn clear(&mut self, session: SessionKey) {
+        if let Some(session) = self.sessions.get_mut(&session) {
+            session.active_search.clear();
+        }
     }
 
-    fn clear(&mut self) {
-        self.active_search.clear();
+    /// Begins a session for a frontend, whose responses are sent to the output given,
+    /// or to the service's own output if `None`.
+    fn connect(&mut self, output: Option<Sender<Response>>) -> SessionKey {
+        let session = self.next_session;
+        self.next_session += 1;
+        self.sessions.insert(session, Session::new(output));
+        self.record(|| Entry::Connect { session });
+        session
     }
 
-    async fn close(&mut self) {
-        self.respond(Response::Close).await;
+    async fn context_response(
+        &mut self,
+        session: SessionKey,
+        id: Indice,
+        options: Vec<ContextOption>,
+    ) {
+        let id = self
+            .sessions
+            .get(&session)
+            .and_then(|session| session.associated_list.get(&id))
+            .copied();
+
+        if let Some(id) = id {
+            self.respond(session, Response::Context { id, options })
+                .await;
+        }
     }
 
-    async fn context_response(&mut self, id: Indice, options: Vec<ContextOption>) {
-        if let Some(id) = self.associated_list.get(&id) {
-            let id = *id;
-            self.respond(Response::Context { id, options }).await;
+    async fn complete(&mut self, session: SessionKey, id: Indice) {
+        if let Some(expansion) = self.hint(session, id) {
+            self.respond(session, Response::Fill(expansion)).await;
+            return;
         }
+
+        self.forward(session, id, Request::Complete).await;
     }
 
-    async fn complete(&mut self, id: Indice) {
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            let _ = plugin
-                .sender_exec()
-                .send_async(Request::Complete(meta.id))
-                .await;
+    async fn context(&mut self, session: SessionKey, id: Indice) {
+        self.forward(session, id, Request::Context).await;
+    }
+
+    /// The text that an alias suggested to the session would expand its query to,
+    /// if the ID is that of a suggested alias.
+    fn hint(&self, session: SessionKey, id: Indice) -> Option<String> {
+        let session = self.sessions.get(&session)?;
+        let position = (Indice::MAX - id) as usize;
+
+        session.hints.get(position).cloned()
+    }
+
+    /// Gives up on plugins which have not finished searching in time, and streams
+    /// the results received so far if no other plugin finished in time.
+    async fn deadline_expired(&mut self) {
+        let now = Instant::now();
+
+        let expired: Vec<(SessionKey, PluginKey)> = self
+            .sessions
+            .iter()
+            .flat_map(|(key, session)| {
+                session
+                    .awaiting_results
+                    .iter()
+                    .filter(|(_, deadline)| **deadline <= now)
+                    .map(move |(plugin, _)| (*key, *plugin))
+            })
+            .collect();
+
+        for (session, plugin) in expired {
+            self.timed_out(session, plugin).await;
+        }
+
+        let pending: Vec<SessionKey> = self
+            .sessions
+            .iter_mut()
+            .filter(|(_, session)| session.update_deadline.is_some_and(|d| d <= now))
+            .map(|(key, session)| {
+                session.update_deadline = None;
+                *key
+            })
+            .collect();
+
+        for session in pending {
+            self.update(session, true).await;
         }
     }
 
-    async fn context(&mut self, id: Indice) {
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            let _ = plugin
-                .sender_exec()
-                .send_async(Request::Context(meta.id))
-                .await;
+    /// Stops the processes of plugins which have not received a request within their
+    /// idle timeout. They are relaunched when next sent a request.
+    async fn stop_idle_plugins(&mut self) {
+        let now = Instant::now();
+
+        for (_, plugin) in self.plugins.iter_mut() {
+            if plugin.idle_deadline().is_none_or(|deadline| deadline > now) {
+                continue;
+            }
+
+            tracing::debug!("{}: stopping idle plugin", plugin.config.name);
+
+            if let Some(sender) = plugin.sender.as_mut() {
+                let _ = sender.send_async(Request::Exit).await;
+            }
+
+            plugin.sender_drop();
+
+            // Results held by the stopped process must be searched for again
+            plugin.searched_for = None;
         }
     }
 
-    async fn fill(&mut self, text: String) {
-        self.respond(Response::Fill(text)).await;
+    /// Ends the session of a frontend which has disconnected.
+    fn disconnect(&mut self, session: SessionKey) {
+        if self.sessions.remove(&session).is_none() {
+            return;
+        }
+
+        self.record(|| Entry::Disconnect { session });
+
+        // Outstanding results for the session will be discarded
+        for (_, plugin) in self.plugins.iter_mut() {
+            plugin.forget(session);
+        }
     }
 
     async fn finished(&mut self, plugin: PluginKey) {
-        self.awaiting_results.remove(&plugin);
-        if !self.awaiting_results.is_empty() {
+        let session = match self.plugins.get_mut(plugin) {
+            Some(conn) => conn.search_ended(),
+            None => None,
+        };
+
+        let session = match session {
+            Some(session) => session,
+            None => return,
+        };
+
+        let awaited = self
+            .sessions
+            .get_mut(&session)
+            .is_some_and(|session| session.awaiting_results.remove(&plugin).is_some());
+
+        if !awaited {
             return;
         }
 
-        if self.search_scheduled {
-            self.search(String::new()).await;
+        if let Some(plugin) = self.plugins.get_mut(plugin) {
+            plugin.search_finished();
+        }
+
+        self.search_progressed(session).await;
+    }
+
+    /// Forwards a request regarding a search result to the plugin which it came from.
+    ///
+    /// Should the plugin have searched for another session since, the session's
+    /// search is repeated so that the plugin's IDs refer to the same results.
+    async fn forward(
+        &mut self,
+        session: SessionKey,
+        id: Indice,
+        request: impl FnOnce(Indice) -> Request,
+    ) {
+        let &mut Self {
+            ref mut plugins,
+            ref sessions,
+            ref settings,
+            ..
+        } = self;
+
+        let state = match sessions.get(&session) {
+            Some(state) => state,
+            None => return,
+        };
+
+        let (plugin, meta) = match state.active_search.get(id as usize) {
+            Some((plugin_id, meta)) => match plugins.get_mut(*plugin_id) {
+                Some(plugin) => (plugin, meta),
+                None => return,
+            },
+            None => return,
+        };
+
+        if plugin.searched_for != Some(session) {
+            let text = alias::expand(&settings.aliases, &state.last_query.text);
+            let query = plugin.payload(&Query::from(text.into_owned()));
+            let query = Request::Search(query);
+
+            if plugin.sender_exec().send_async(query).await.is_ok() {
+                plugin.search_sent(None);
+                plugin.searched_for = Some(session);
+            }
+        }
+
+        plugin.requester = Some(session);
+
+        let _ = plugin.sender_exec().send_async(request(meta.id)).await;
+    }
+
+    /// Sends results once all plugins have finished searching, or begins the next
+    /// search if one was scheduled in the meantime.
+    async fn search_progressed(&mut self, key: SessionKey) {
+        let session = match self.sessions.get_mut(&key) {
+            Some(session) => session,
+            None => return,
+        };
+
+        if !session.awaiting_results.is_empty() {
+            if self.settings.stream_results
+                && session.capabilities.partial_update
+                && !session.search_scheduled
+                && session.update_deadline.is_none()
+            {
+                let debounce = Duration::from_millis(self.settings.stream_debounce);
+                session.update_deadline = Some(Instant::now() + debounce);
+            }
+
             return;
         }
 
-        let search_list = self.sort();
+        session.update_deadline = None;
 
-        self.respond(Response::Update(search_list)).await;
+        if session.search_scheduled {
+            self.search(key, Query::default()).await;
+            return;
+        }
+
+        self.update(key, false).await;
     }
 
-    async fn interrupt(&mut self) {
+    /// Interrupts the searches of a session, sparing plugins which are still
+    /// searching for other sessions.
+    async fn interrupt(&mut self, session: SessionKey) {
         for (_, plugin) in self.plugins.iter_mut() {
+            let exclusive = plugin
+                .searches
+                .iter()
+                .all(|searcher| searcher.is_none_or(|searcher| searcher == session));
+
+            if !exclusive {
+                continue;
+            }
+
             if let Some(sender) = plugin.sender.as_mut() {
                 let _ = sender.send_async(Request::Interrupt).await;
             }
         }
     }
 
-    async fn quit(&mut self, id: Indice) {
-        if let Some((plugin, meta)) = self.search_result(id as usize) {
-            let _ = plugin
-                .sender_exec()
-                .send_async(Request::Quit(meta.id))
-                .await;
+    /// Treats a plugin which failed to finish its search in time as finished,
+    /// and disables it if it has failed to do so too often.
+    async fn timed_out(&mut self, session: SessionKey, key: PluginKey) {
+        if let Some(state) = self.sessions.get_mut(&session) {
+            state.awaiting_results.remove(&key);
+        }
+
+        let mut failed = false;
+
+        if let Some(plugin) = self.plugins.get_mut(key) {
+            tracing::error!("{}: timed out while searching", plugin.config.name);
+            plugin.search_timed_out();
+            plugin.abandon(session);
+
+            if plugin.disabled {
+                failed = true;
+
+                tracing::error!(
+                    "{}: disabled after failing to respond to searches",
+                    plugin.config.name
+                );
+
+                if let Some(sender) = plugin.sender.as_mut() {
+                    let _ = sender.send_async(Request::Exit).await;
+                }
+
+                plugin.sender_drop();
+            } else if let Some(sender) = plugin.sender.as_mut() {
+                let _ = sender.send_async(Request::Interrupt).await;
+            }
+        }
+
+        if failed {
+            self.report_failure(key).await;
+            self.abandon_searches(key, usize::MAX).await;
+        }
+
+        self.search_progressed(session).await;
+    }
+
+    /// Treats the oldest unfinished searches of a plugin as finished, as the plugin
+    /// stopped or was disabled before finishing them.
+    async fn abandon_searches(&mut self, key: PluginKey, count: usize) {
+        let sessions = match self.plugins.get_mut(key) {
+            Some(plugin) => plugin.abandon_oldest(count),
+            None => return,
+        };
+
+        for session in sessions {
+            let awaited = self
+                .sessions
+                .get_mut(&session)
+                .is_some_and(|session| session.awaiting_results.remove(&key).is_some());
+
+            if awaited {
+                self.search_progressed(session).await;
+            }
+        }
+    }
+
+    /// Notifies every frontend that a plugin has been disabled, and why.
+    async fn report_failure(&mut self, key: PluginKey) {
+        let (plugin, reason) = match self.plugins.get(key) {
+            Some(plugin) => (
+                plugin.config.name.to_string(),
+                plugin.failure.clone().unwrap_or_default(),
+            ),
+            None => return,
+        };
+
+        let sessions: Vec<SessionKey> = self.sessions.keys().copied().collect();
+
+        for session in sessions {
+            let response = Response::PluginFailed {
+                plugin: plugin.clone(),
+                reason: reason.clone(),
+            };
+
+            self.respond(session, response).await;
+        }
+    }
+
+    /// Records what a frontend understands, and introduces the service in return.
+    async fn hello(&mut self, session: SessionKey, capabilities: Capabilities) {
+        match self.sessions.get_mut(&session) {
+            Some(session) => session.capabilities = capabilities,
+            None => return,
         }
+
+        self.respond(session, Response::Hello(Capabilities::current()))
+            .await;
+    }
+
+    /// Fills in a query which led to an activation, stepping from the newest query
+    /// to the oldest, and then back to the text which was typed.
+    async fn recall_query(&mut self, key: SessionKey, direction: Direction) {
+        let &mut Self {
+            ref mut sessions,
+            ref recall,
+            ..
+        } = self;
+
+        let session = match sessions.get_mut(&key) {
+            Some(session) => session,
+            None => return,
+        };
+
+        let text = &session.last_query.text;
+
+        // Recalling begins anew once something else has been typed
+        let mut recalling = session
+            .recalling
+            .take()
+            .filter(|recalling| recalling.filled == *text || recalling.typed == *text)
+            .unwrap_or_else(|| Recalling {
+                typed: text.clone(),
+                position: 0,
+                filled: text.clone(),
+            });
+
+        let queries = recall.matching(&recalling.typed);
+
+        let position = match direction {
+            Direction::Previous => (recalling.position + 1).min(queries.len()),
+            Direction::Next => recalling.position.saturating_sub(1),
+        };
+
+        if position == recalling.position {
+            session.recalling = Some(recalling);
+            return;
+        }
+
+        let fill = match position {
+            0 => recalling.typed.clone(),
+            n => queries[n - 1].to_owned(),
+        };
+
+        recalling.position = position;
+        recalling.filled = fill.clone();
+        session.recalling = Some(recalling);
+
+        self.respond(key, Response::Fill(fill)).await;
     }
 
-    async fn respond(&mut self, event: Response) {
-        let _ = self.output.send(event).await;
+    /// Records an entry, if the service is being recorded.
+    fn record(&self, entry: impl FnOnce() -> Entry) {
+        if let Some(recorder) = self.recorder.as_ref() {
+            recorder.record(entry());
+        }
+    }
+
+    /// Answers a frontend with the latency and health of each plugin.
+    async fn stats(&mut self, session: SessionKey) {
+        let mut stats: Vec<PluginStats> = self.plugins.iter().map(|(_, p)| p.stats()).collect();
+        stats.sort_by(|a, b| a.plugin.cmp(&b.plugin));
+
+        self.respond(session, Response::Stats(stats)).await;
     }
 
-    async fn search(&mut self, query: String) {
-        if !self.awaiting_results.is_empty() {
+    /// Forwards an error reported by a plugin to the session it concerns.
+    async fn plugin_error(
+        &mut self,
+        key: PluginKey,
+        session: Option<SessionKey>,
+        kind: ErrorKind,
+        message: String,
+    ) {
+        let plugin = match self.plugins.get(key) {
+            Some(plugin) => plugin.config.name.to_string(),
+            None => return,
+        };
+
+        tracing::warn!("{}: {:?}: {}", plugin, kind, message);
+
+        if let Some(session) = session {
+            let (legacy, searching) = match self.sessions.get(&session) {
+                Some(session) => (
+                    !session.capabilities.error,
+                    session.awaiting_results.contains_key(&key),
+                ),
+                None => return,
+            };
+
+            // Frontends which predate errors are told of them as plugins once did,
+            // with a result whose name is the message.
+            if legacy {
+                let result = PluginSearchResult {
+                    name: message,
+                    ..Default::default()
+                };
+
+                self.append(session, key, result);
+
+                if !searching {
+                    self.update(session, false).await;
+                }
+
+                return;
+            }
+
+            let response = Response::Error {
+                plugin,
+                kind,
+                message,
+            };
+
+            self.respond(session, response).await;
+        }
+    }
+
+    async fn quit(&mut self, session: SessionKey, id: Indice) {
+        self.forward(session, id, Request::Quit).await;
+    }
+
+    async fn respond(&mut self, key: SessionKey, event: Response) {
+        let session = match self.sessions.get(&key) {
+            Some(session) => session,
+            None => return,
+        };
+
+        // Responses which the frontend would fail to deserialize are withheld
+        if !session.capabilities.understands(&event) {
+            return;
+        }
+
+        self.record(|| Entry::Response {
+            session: key,
+            response: event.clone(),
+        });
+
+        match &session.output {
+            // A frontend which stopped reading must not stall every other session
+            Some(output) => {
+                if output.try_send(event).is_err() {
+                    tracing::warn!("disconnecting session {}, which stopped reading", key);
+                    self.disconnect(key);
+                }
+            }
+            None => {
+                let _ = self.output.send(event).await;
+            }
+        }
+    }
+
+    async fn search(&mut self, key: SessionKey, query: Query) {
+        let session = match self.sessions.get_mut(&key) {
+            Some(session) => session,
+            None => return,
+        };
+
+        if !session.awaiting_results.is_empty() {
             tracing::debug!("backing off from search until plugins are ready");
-            if !self.search_scheduled {
-                self.interrupt().await;
-                self.search_scheduled = true;
-                self.last_query = query;
+            if !session.search_scheduled {
+                session.search_scheduled = true;
+                session.update_deadline = None;
+                session.last_query = query;
+                self.interrupt(key).await;
             }
 
             return;
         }
 
-        self.active_search.clear();
+        session.active_search.clear();
 
-        if !self.search_scheduled {
-            self.last_query = query;
+        if !session.search_scheduled {
+            session.last_query = query;
         }
 
-        self.search_scheduled = false;
-        let query = self.last_query.as_str();
+        session.search_scheduled = false;
+        session.isolated = None;
+        session.no_sort = false;
+
+        self.search_id = self.search_id.wrapping_add(1);
+        session.search_id = self.search_id;
+
+        // Plugins are matched against, and search for, the query which aliases expand to
+        let query = Query {
+            text: alias::expand(&self.settings.aliases, &session.last_query.text).into_owned(),
+            id: Some(self.search_id),
+            trigger: None,
+            keep_accents: !self.settings.accent_insensitive,
+        };
 
         let mut query_queue = Vec::new();
         let mut isolated = None;
@@ -366,9 +1294,13 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
         let requires_persistence = query.is_empty();
 
         for (key, plugin) in self.plugins.iter_mut() {
+            if plugin.disabled {
+                continue;
+            }
+
             // Avoid sending queries to plugins which are not matched
             if let Some(regex) = plugin.regex.as_ref() {
-                if !regex.is_match(query) {
+                if !regex.is_match(&query) {
                     continue;
                 }
             }
@@ -383,7 +1315,7 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
             }
 
             if let Some(regex) = plugin.isolate_regex.as_ref() {
-                if regex.is_match(query) {
+                if regex.is_match(&query) {
                     isolated = Some(key);
                     break;
                 }
@@ -394,113 +1326,200 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
 
         if let Some(isolated) = isolated {
             if let Some(plugin) = self.plugins.get_mut(isolated) {
+                let query = plugin.payload(&query);
+
                 if plugin
                     .sender_exec()
-                    .send_async(Request::Search(query.to_owned()))
+                    .send_async(Request::Search(query))
                     .await
                     .is_ok()
                 {
-                    self.awaiting_results.insert(isolated);
-                    self.no_sort = plugin.config.query.no_sort;
+                    plugin.searching_for(key);
+                    session
+                        .awaiting_results
+                        .insert(isolated, search_deadline(&plugin.config));
+                    session.isolated = Some(isolated);
+                    session.no_sort = plugin.config.query.no_sort;
                 }
             }
         } else {
             for plugin_id in query_queue {
                 if let Some(plugin) = self.plugins.get_mut(plugin_id) {
+                    let query = plugin.payload(&query);
+
                     if plugin
                         .sender_exec()
-                        .send_async(Request::Search(query.to_owned()))
+                        .send_async(Request::Search(query))
                         .await
                         .is_ok()
                     {
-                        self.awaiting_results.insert(plugin_id);
+                        plugin.searching_for(key);
+                        let deadline = search_deadline(&plugin.config);
+                        session.awaiting_results.insert(plugin_id, deadline);
                     }
                 }
             }
         }
+
+        // Aliases are suggested even when no plugin searches for the query
+        if session.awaiting_results.is_empty()
+            && !alias::hints(&self.settings.aliases, &session.last_query.text).is_empty()
+        {
+            self.update(key, false).await;
+        }
+    }
+
+    /// Sends a slice of the sorted results of the session's latest search, beyond
+    /// those which its update was limited to. Offsets count over the same sequence
+    /// as the update, so the alias hints it led with are counted before the results.
+    async fn page(&mut self, key: SessionKey, offset: usize, limit: usize) {
+        let &mut Self {
+            ref mut sessions,
+            ref plugins,
+            ref settings,
+            ..
+        } = self;
+
+        let &mut Session {
+            ref active_search,
+            ref mut associated_list,
+            ref hints,
+            ref last_query,
+            ..
+        } = match sessions.get_mut(&key) {
+            Some(session) => session,
+            None => return,
+        };
+
+        let suggested = alias::hints(&settings.aliases, &last_query.text)
+            .into_iter()
+            .take(hints.len())
+            .enumerate()
+            .map(|(position, (alias, expansion))| hint_result(position, alias, expansion));
+
+        let total = hints.len() + displayable(plugins, active_search).count();
+
+        let search_results = displayable(plugins, active_search).map(|(id, result)| {
+            associated_list.insert(result.1.id, id as u32);
+            frontend_result(plugins, id, result)
+        });
+
+        let results = suggested
+            .chain(search_results)
+            .skip(offset)
+            .take(limit)
+            .collect();
+
+        let response = Response::Page {
+            results,
+            offset,
+            total,
+            id: last_query.id,
+        };
+
+        self.respond(key, response).await;
+    }
+
+    /// Sends the sorted search results to the frontend, noting whether more are pending
+    /// when results are being streamed.
+    async fn update(&mut self, session: SessionKey, pending: bool) {
+        let results = self.sort(session);
+
+        let (id, stream) = match self.sessions.get(&session) {
+            Some(session) => (
+                session.last_query.id,
+                self.settings.stream_results && session.capabilities.partial_update,
+            ),
+            None => return,
+        };
+
+        let response = if stream {
+            Response::PartialUpdate {
+                results,
+                pending,
+                id,
+            }
+        } else {
+            Response::Update(SearchResults { results, id })
+        };
+
+        self.respond(session, response).await;
     }
 
     /// From a given position ID, fetch the search result and its associated plugin
     fn search_result(
         &mut self,
+        session: SessionKey,
         id: usize,
     ) -> Option<(&mut PluginConnector, &mut PluginSearchResult)> {
         let &mut Self {
-            ref mut active_search,
+            ref mut sessions,
             ref mut plugins,
             ..
         } = self;
 
-        active_search
-            .get_mut(id)
+        sessions
+            .get_mut(&session)
+            .and_then(|session| session.active_search.get_mut(id))
             .and_then(move |(plugin_id, meta)| {
                 plugins.get_mut(*plugin_id).map(|plugin| (plugin, meta))
             })
     }
 
-    fn sort(&mut self) -> Vec<SearchResult> {
+    fn sort(&mut self, session: SessionKey) -> Vec<SearchResult> {
         let &mut Self {
-            ref mut active_search,
-            ref mut associated_list,
-            ref mut no_sort,
-            ref last_query,
+            ref mut sessions,
+            ref history,
             ref plugins,
+            ref scorer,
+            ref settings,
             ..
         } = self;
 
-        let query = &last_query.to_ascii_lowercase();
-
-        use std::cmp::Ordering;
-
-        if *no_sort {
-            *no_sort = false;
-        } else {
-            active_search.sort_by(|a, b| {
-                // Weight is calculated between 0.0 and 1.0, with higher values being most similar
-                fn calculate_weight(meta: &PluginSearchResult, query: &str) -> f64 {
-                    let mut weight: f64 = 0.0;
-
-                    let name = meta.name.to_ascii_lowercase();
-                    let description = meta.description.to_ascii_lowercase();
-                    let exec = meta
-                        .exec
-                        .as_ref()
-                        .map(|exec| exec.to_ascii_lowercase())
-                        .unwrap_or_default();
-
-                    for name in name.split_ascii_whitespace().flat_map(|x| x.split('_')) {
-                        if name.starts_with(query) {
-                            return 1.0;
-                        }
-                    }
+        let &mut Session {
+            ref mut active_search,
+            ref mut associated_list,
+            ref mut hints,
+            ref no_sort,
+            ref isolated,
+            ref last_query,
+            ..
+        } = match sessions.get_mut(&session) {
+            Some(session) => session,
+            None => return Vec::new(),
+        };
 
-                    if exec.contains(query) {
-                        if exec.starts_with(query) {
-                            return 1.0;
-                        } else {
-                            weight = strsim::jaro_winkler(query, &exec) - 0.1;
-                        }
-                    }
+        let query = alias::expand(&settings.aliases, &last_query.text);
+        let query = &normalize::fold_with(&query, settings.accent_insensitive);
 
-                    weight
-                        .max(strsim::jaro_winkler(&name, query))
-                        .max(strsim::jaro_winkler(&description, query) - 0.1)
-                        .max(match meta.keywords.as_ref() {
-                            Some(keywords) => keywords
-                                .iter()
-                                .flat_map(|word| word.split_ascii_whitespace())
-                                .fold(0.0, |acc, keyword| {
-                                    let keyword = keyword.to_ascii_lowercase();
-                                    acc.max(strsim::jaro_winkler(query, &keyword) - 0.1)
-                                }),
-                            None => 0.0,
-                        })
-                }
+        dedupe::dedupe(active_search, |plugin| {
+            plugins
+                .get(plugin)
+                .map_or(PluginPriority::Low, |plugin| plugin.config.query.priority)
+        });
 
-                let a_weight = calculate_weight(&a.1, query);
-                let b_weight = calculate_weight(&b.1, query);
+        use std::cmp::Ordering;
 
-                match a_weight.partial_cmp(&b_weight) {
+        if !*no_sort {
+            // Results from plugins with history enabled are boosted by how often,
+            // and how recently, they have been activated
+            let frecency = |(plugin, meta): &(PluginKey, PluginSearchResult)| {
+                plugins
+                    .get(*plugin)
+                    .filter(|plugin| plugin.config.history)
+                    .map_or(0.0, |plugin| {
+                        FRECENCY_WEIGHT * history.frecency(&plugin.config.name, &meta.name)
+                    })
+            };
+
+            // Weights are calculated once, rather than on every comparison
+            let mut weighted: Vec<(f64, (PluginKey, PluginSearchResult))> = active_search
+                .drain(..)
+                .map(|result| (scorer.score(query, &result.1) + frecency(&result), result))
+                .collect();
+
+            weighted.sort_by(
+                |(a_weight, a), (b_weight, b)| match a_weight.partial_cmp(b_weight) {
                     Some(Ordering::Equal) => {
                         let a_len = a.1.name.len();
                         let b_len = b.1.name.len();
@@ -510,8 +1529,10 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
                     Some(Ordering::Less) => Ordering::Greater,
                     Some(Ordering::Greater) => Ordering::Less,
                     None => Ordering::Greater,
-                }
-            });
+                },
+            );
+
+            active_search.extend(weighted.into_iter().map(|(_, result)| result));
 
             active_search.sort_by(|a, b| {
                 let plug1 = match plugins.get(a.0) {
@@ -532,37 +1553,32 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
             })
         }
 
-        let take = if last_query.starts_with('/') | last_query.starts_with('~') {
-            100
-        } else {
-            8
-        };
+        // A plugin's own limit replaces the global limit when its query is isolated.
+        let limit = isolated
+            .and_then(|key| plugins.get(key))
+            .and_then(|plugin| plugin.config.query.max_results)
+            .unwrap_or(settings.max_results);
+
+        // Aliases are suggested ahead of the results, so that they may be discovered,
+        // taking their place within the limit
+        let mut suggested = alias::hints(&settings.aliases, &last_query.text);
+        suggested.truncate(limit);
+
+        let take = limit - suggested.len();
 
         let mut windows = Vec::with_capacity(take);
         let mut non_windows = Vec::with_capacity(take);
         associated_list.clear();
 
-        let search_results =
-            active_search
-                .iter()
-                .take(take)
-                .enumerate()
-                .map(|(id, (plugin, meta))| {
-                    associated_list.insert(meta.id, id as u32);
-                    SearchResult {
-                        id: id as u32,
-                        name: meta.name.clone(),
-                        description: meta.description.clone(),
-                        icon: meta.icon.clone(),
-                        category_icon: plugins
-                            .get(*plugin)
-                            .and_then(|conn| conn.config.icon.clone()),
-                        window: meta.window,
-                    }
-                });
+        let search_results = displayable(plugins, active_search)
+            .take(take)
+            .map(|(id, result)| {
+                associated_list.insert(result.1.id, id as u32);
+                frontend_result(plugins, id, result)
+            });
 
         for result in search_results {
-            if result.window.is_some() {
+            if settings.hoist_windows && result.window.is_some() {
                 windows.push(result);
             } else {
                 non_windows.push(result)
@@ -570,12 +1586,111 @@ impl<O: futures::Sink<Response> + Unpin> Service<O> {
         }
 
         windows.append(&mut non_windows);
-        windows
+
+        hints.clear();
+
+        let mut results: Vec<SearchResult> = suggested
+            .into_iter()
+            .enumerate()
+            .map(|(position, (alias, expansion))| {
+                hints.push(expansion.clone());
+                hint_result(position, alias, expansion)
+            })
+            .collect();
+
+        results.append(&mut windows);
+        results
     }
 }
 
+/// The prefix which directed a query to a plugin, or empty if the plugin has none.
+fn query_prefix(plugin: &PluginConnector, text: &str) -> String {
+    if let Some(trigger) = plugin.payload(&Query::from(text)).trigger {
+        if !trigger.is_empty() {
+            return trigger;
+        }
+    }
+
+    plugin
+        .config
+        .query
+        .help
+        .as_deref()
+        .map(str::trim)
+        .filter(|help| !help.is_empty() && text.starts_with(help))
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// The sorted results of a search which may be displayed, and their positions,
+/// once the limit of each plugin on how many of its results are displayed is applied.
+fn displayable<'a>(
+    plugins: &'a Slab<PluginConnector>,
+    results: &'a [(PluginKey, PluginSearchResult)],
+) -> impl Iterator<Item = (usize, &'a (PluginKey, PluginSearchResult))> + 'a {
+    let mut plugin_counts = HashMap::new();
+
+    results.iter().enumerate().filter(move |(_, (plugin, _))| {
+        let count = plugin_counts.entry(*plugin).or_insert(0);
+        *count += 1;
+
+        plugins
+            .get(*plugin)
+            .and_then(|conn| conn.config.query.max_results)
+            .is_none_or(|max| *count <= max)
+    })
+}
+
+/// A result as it is sent to the frontend, identified by its position in the search.
+fn frontend_result(
+    plugins: &Slab<PluginConnector>,
+    id: usize,
+    (plugin, meta): &(PluginKey, PluginSearchResult),
+) -> SearchResult {
+    SearchResult {
+        id: id as u32,
+        name: meta.name.clone(),
+        description: meta.description.clone(),
+        icon: meta.icon.clone(),
+        category_icon: plugins
+            .get(*plugin)
+            .and_then(|conn| conn.config.icon.clone()),
+        window: meta.window,
+    }
+}
+
+/// Suggested aliases are identified from the top of the range of IDs, as results
+/// are identified by their position from the bottom.
+fn hint_id(position: usize) -> Indice {
+    Indice::MAX - position as Indice
+}
+
+/// The result which suggests an alias, at the given position among the hints.
+fn hint_result(position: usize, alias: &str, expansion: String) -> SearchResult {
+    SearchResult {
+        id: hint_id(position),
+        name: alias.to_owned(),
+        description: expansion,
+        icon: Some(IconSource::Name(Cow::Borrowed(
+            "edit-find-replace-symbolic",
+        ))),
+        category_icon: None,
+        window: None,
+    }
+}
+
+/// The instant by which a plugin must finish a search that begins now.
+fn search_deadline(config: &PluginConfig) -> Instant {
+    let timeout = config.query.timeout.unwrap_or(SEARCH_TIMEOUT);
+    Instant::now() + Duration::from_millis(timeout)
+}
+
 /// Handles Requests received from a frontend
-async fn request_handler(input: impl Stream<Item = Request>, tx: Sender<Event>) {
+async fn request_handler(
+    session: SessionKey,
+    input: impl Stream<Item = Request>,
+    tx: Sender<Event>,
+) {
     let mut requested_to_exit = false;
 
     futures::pin_mut!(input);
@@ -585,7 +1700,7 @@ async fn request_handler(input: impl Stream<Item = Request>, tx: Sender<Event>)
             requested_to_exit = true
         }
 
-        let _ = tx.send_async(Event::Request(request)).await;
+        let _ = tx.send_async(Event::Request(session, request)).await;
 
         if requested_to_exit {
             break;
@@ -602,3 +1717,173 @@ fn serialize_out<E: serde::Serialize>(output: &mut io::StdoutLock, event: &E) {
         let _ = output.write_all(&vec);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_plugins_are_stopped_until_needed() {
+        let mut service = Service::new(futures::sink::drain());
+
+        // Requests received by each launch of the plugin
+        let launches: Arc<Mutex<Vec<Receiver<Request>>>> = Arc::default();
+
+        let config = PluginConfig {
+            name: Cow::Borrowed("Idle"),
+            idle_timeout: Some(1),
+            ..PluginConfig::default()
+        };
+
+        let launches_ = launches.clone();
+        let key = service.plugins.insert(PluginConnector::new(
+            config.clone(),
+            config,
+            None,
+            None,
+            Box::new(move || {
+                let (tx, rx) = flume::unbounded();
+                launches_.lock().unwrap().push(rx);
+                tx
+            }),
+        ));
+
+        service.plugins[key].sender_exec();
+        let launched = Instant::now();
+        assert_eq!(Some(launched + Duration::from_secs(1)), service.deadline());
+
+        // A plugin which is searching is not idle
+        service.plugins[key].searches.push_back(None);
+        assert_eq!(None, service.deadline());
+        service.plugins[key].searches.clear();
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        service.deadline_reached().await;
+
+        assert!(service.plugins[key].sender.is_none());
+        assert_eq!(None, service.deadline());
+        assert!(matches!(
+            launches.lock().unwrap()[0].try_recv(),
+            Ok(Request::Exit)
+        ));
+
+        // And it is launched again once it is needed
+        service.plugins[key].sender_exec();
+        assert_eq!(2, launches.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn removed_overrides_are_reverted() {
+        let mut service = Service::new(futures::sink::drain());
+        let (service_tx, _service_rx) = flume::bounded(1);
+
+        let installed = PluginConfig {
+            name: Cow::Borrowed("Help"),
+            ..PluginConfig::default()
+        };
+
+        let overlay = PluginOverlay {
+            priority: Some(PluginPriority::High),
+            ..PluginOverlay::default()
+        };
+
+        service
+            .settings
+            .plugins
+            .insert(String::from("Help"), overlay);
+
+        let key = service.register_plugin(
+            service_tx.clone(),
+            installed.clone(),
+            None,
+            plugins::help::HelpPlugin::new,
+        );
+
+        assert_eq!(installed, service.plugins[key].loaded);
+        assert_eq!(
+            PluginPriority::High,
+            service.plugins[key].config.query.priority
+        );
+
+        // The override is removed from launcher.ron
+        let previous = std::mem::take(&mut service.settings);
+        assert!(service.settings.overrides_changed(&previous, "Help"));
+
+        // And the plugin is reloaded from its config as it was installed
+        let loaded = service.plugins[key].loaded.clone();
+        service.unregister_plugin(key).await;
+        let key = service.register_plugin(service_tx, loaded, None, plugins::help::HelpPlugin::new);
+
+        assert_eq!(installed, service.plugins[key].config);
+    }
+
+    #[tokio::test]
+    async fn events_of_unloaded_plugins_are_dropped() {
+        let mut service = Service::new(futures::sink::drain());
+        let (service_tx, _service_rx) = flume::unbounded();
+
+        let config = PluginConfig {
+            name: Cow::Borrowed("Help"),
+            ..PluginConfig::default()
+        };
+
+        let key = service.register_plugin(
+            service_tx.clone(),
+            config.clone(),
+            None,
+            plugins::help::HelpPlugin::new,
+        );
+
+        let unloaded = service.plugin_id(key);
+        service.unregister_plugin(key).await;
+
+        // The key is reused by the plugin which is loaded next
+        let key = service.register_plugin(service_tx, config, None, plugins::help::HelpPlugin::new);
+        assert_eq!(unloaded.key, key);
+
+        let session = service.connect(None);
+        service
+            .sessions
+            .get_mut(&session)
+            .unwrap()
+            .awaiting_results
+            .insert(key, Instant::now());
+        service.plugins[key].searches.push_back(Some(session));
+
+        // A late response of the unloaded plugin is not credited to its successor
+        service
+            .handle(Event::Response((unloaded, PluginResponse::Finished)))
+            .await;
+
+        assert!(service.sessions[&session]
+            .awaiting_results
+            .contains_key(&key));
+
+        // And a plugin which deactivates twice is removed once
+        let loaded = service.plugin_id(key);
+
+        for _ in 0..2 {
+            service
+                .handle(Event::Response((loaded, PluginResponse::Deactivate)))
+                .await;
+        }
+
+        assert!(!service.plugins.contains(key));
+        assert!(service.sessions[&session].awaiting_results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn frontends_which_stop_reading_are_disconnected() {
+        let mut service = Service::new(futures::sink::drain());
+
+        let (output, _output_rx) = flume::bounded(1);
+        let session = service.connect(Some(output));
+
+        service.respond(session, Response::Close).await;
+        assert!(service.sessions.contains_key(&session));
+
+        service.respond(session, Response::Close).await;
+        assert!(!service.sessions.contains_key(&session));
+    }
+}
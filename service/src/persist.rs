@@ -0,0 +1,52 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Writes files away from the service's thread, so that it is not stalled by the disk.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Writes the contents of a file on the blocking thread pool. Contents saved while
+/// a write is queued replace those of the queued write, and writes never overtake
+/// one another, so the newest contents are the last written.
+#[derive(Debug, Default)]
+pub struct Persist {
+    /// Contents waiting to be written
+    pending: Arc<Mutex<Option<String>>>,
+    /// Held while writing, so that older contents never replace newer
+    writing: Arc<Mutex<()>>,
+}
+
+impl Persist {
+    pub fn write(&self, path: PathBuf, contents: String) {
+        let queued = match self.pending.lock() {
+            Ok(mut pending) => pending.replace(contents).is_some(),
+            Err(_) => return,
+        };
+
+        // The write already queued takes these contents instead
+        if queued {
+            return;
+        }
+
+        let pending = self.pending.clone();
+        let writing = self.writing.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let _writing = writing.lock();
+
+            let contents = match pending.lock().ok().and_then(|mut pending| pending.take()) {
+                Some(contents) => contents,
+                None => return,
+            };
+
+            if let Some(parent) = path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+
+            if let Err(why) = std::fs::write(&path, contents) {
+                tracing::error!("failed to write {}: {}", path.display(), why);
+            }
+        });
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Plugins bundled with the launcher, run within the service rather than in a
+//! process of their own.
+//!
+//! Such plugins give up the crash isolation of a process. Release builds abort on
+//! panic, so a plugin which panics there takes the service down with it.
+
+use crate::{Event, Indice, Plugin, PluginBinary, PluginId, PluginResponse, Query, Request};
+use flume::{Receiver, Sender};
+use futures::future::LocalBoxFuture;
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Runs a plugin, given the channels which its requests are received from and its
+/// responses are sent to.
+pub type Builtin = fn(Receiver<Request>, Sender<PluginResponse>) -> LocalBoxFuture<'static, ()>;
+
+/// The command of the multicall binary which runs a plugin, if the binary is the one
+/// which the service is running from, and the plugin needs no process of its own.
+pub fn command(bin: &PluginBinary) -> Option<&str> {
+    let isolated = bin.args().next().is_some()
+        || bin.env().next().is_some()
+        || bin.env_allow().is_some()
+        || bin.cwd().is_some()
+        || *bin.limits() != Default::default();
+
+    if isolated {
+        return None;
+    }
+
+    let path = bin.path();
+    let current = std::env::current_exe().ok()?;
+
+    if !same_file(path, &current) {
+        return None;
+    }
+
+    path.file_name()?.to_str()
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+pub struct BuiltinPlugin {
+    id: PluginId,
+    name: String,
+    main: Builtin,
+    tx: Sender<Event>,
+    /// Sends requests to the running plugin, if it has been launched
+    requests: Option<Sender<Request>>,
+    /// Searches sent to the running plugin which it has yet to finish
+    unfinished: Arc<AtomicUsize>,
+}
+
+impl BuiltinPlugin {
+    pub fn new(id: PluginId, name: String, main: Builtin, tx: Sender<Event>) -> Self {
+        Self {
+            id,
+            name,
+            main,
+            tx,
+            requests: None,
+            unfinished: Arc::default(),
+        }
+    }
+
+    /// Runs the plugin on a thread of its own, so that it may block without
+    /// stalling the service, and forwards its responses to the service.
+    fn launch(&mut self) -> Option<&Sender<Request>> {
+        tracing::debug!("{}: launching builtin plugin", self.name);
+
+        let (request_tx, request_rx) = flume::unbounded();
+        let (response_tx, response_rx) = flume::unbounded();
+
+        let main = self.main;
+        let name = self.name.clone();
+
+        let thread = std::thread::Builder::new()
+            .name(self.name.clone())
+            .spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build();
+
+                match runtime {
+                    Ok(runtime) => runtime.block_on(main(request_rx, response_tx)),
+                    Err(why) => tracing::error!("{}: failed to create runtime: {}", name, why),
+                }
+            });
+
+        let thread = match thread {
+            Ok(thread) => thread,
+            Err(why) => {
+                tracing::error!("{}: failed to spawn thread: {}", self.name, why);
+                return None;
+            }
+        };
+
+        let id = self.id;
+        let name = self.name.clone();
+        let tx = self.tx.clone();
+
+        self.unfinished = Arc::default();
+        let unfinished = self.unfinished.clone();
+
+        tokio::spawn(async move {
+            while let Ok(response) = response_rx.recv_async().await {
+                match response {
+                    // Bundled plugins speak the same protocol as the service
+                    PluginResponse::Hello(_) => continue,
+                    PluginResponse::Finished => {
+                        let _ = unfinished
+                            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+                    }
+                    _ => (),
+                }
+
+                let _ = tx.send_async(Event::Response((id, response))).await;
+            }
+
+            // A plugin which stopped will not finish its searches
+            let searches = unfinished.swap(0, Ordering::SeqCst);
+            if searches > 0 {
+                let _ = tx.send_async(Event::PluginExit(id, searches)).await;
+            }
+
+            // Only builds which unwind on panic survive to see this
+            let panicked = tokio::task::spawn_blocking(move || thread.join().is_err())
+                .await
+                .unwrap_or(true);
+
+            if panicked {
+                tracing::error!("{}: builtin plugin panicked", name);
+                let _ = tx.send_async(Event::PluginCrashed(id)).await;
+            }
+
+            tracing::debug!("{}: builtin plugin stopped", name);
+        });
+
+        self.requests = Some(request_tx);
+        self.requests.as_ref()
+    }
+
+    async fn query(&mut self, request: Request) -> bool {
+        // A plugin which stopped on its own is launched again
+        let requests = match self.requests.as_ref() {
+            Some(requests) if !requests.is_disconnected() => Some(requests.clone()),
+            _ => self.launch().cloned(),
+        };
+
+        let requests = match requests {
+            Some(requests) => requests,
+            None => return false,
+        };
+
+        // Counted beforehand, as the plugin may finish before the request is sent
+        let searching = matches!(request, Request::Search(_));
+        if searching {
+            self.unfinished.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let sent = requests.send_async(request).await.is_ok();
+
+        if searching && !sent {
+            self.unfinished.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        sent
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for BuiltinPlugin {
+    async fn activate(&mut self, id: Indice) {
+        self.query(Request::Activate(id)).await;
+    }
+
+    async fn activate_context(&mut self, id: Indice, context: Indice) {
+        self.query(Request::ActivateContext { id, context }).await;
+    }
+
+    async fn complete(&mut self, id: Indice) {
+        self.query(Request::Complete(id)).await;
+    }
+
+    async fn context(&mut self, id: Indice) {
+        self.query(Request::Context(id)).await;
+    }
+
+    fn exit(&mut self) {
+        if let Some(requests) = self.requests.take() {
+            let _ = requests.try_send(Request::Exit);
+        }
+    }
+
+    async fn interrupt(&mut self) {
+        self.query(Request::Interrupt).await;
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&mut self, query: &Query) {
+        if !self.query(Request::Search(query.clone())).await {
+            let _ = self
+                .tx
+                .send_async(Event::Response((self.id, PluginResponse::Finished)))
+                .await;
+        }
+    }
+
+    async fn quit(&mut self, id: Indice) {
+        self.query(Request::Quit(id)).await;
+    }
+}
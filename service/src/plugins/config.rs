@@ -2,13 +2,14 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
+    collections::HashMap,
     path::{Path, PathBuf},
 };
 
-#[derive(Debug, Default, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
 pub struct PluginConfig {
     pub name: Cow<'static, str>,
     pub description: Cow<'static, str>,
@@ -25,6 +26,15 @@ pub struct PluginConfig {
     )]
     pub icon: Option<crate::IconSource>,
 
+    /// Seconds without requests after which the plugin's process is stopped, to be
+    /// relaunched when it is next needed.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub idle_timeout: Option<u64>,
+
     #[serde(default)]
     pub query: PluginQuery,
 
@@ -32,16 +42,131 @@ pub struct PluginConfig {
     pub history: bool,
 }
 
-#[derive(Debug, Default, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
 pub struct PluginBinary {
     path: Cow<'static, str>,
 
     #[serde(default)]
-    #[allow(dead_code)]
     args: Vec<Cow<'static, str>>,
+
+    /// Working directory of the plugin's process.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    cwd: Option<Cow<'static, str>>,
+
+    /// Variables set in the environment of the plugin's process.
+    #[serde(default)]
+    env: HashMap<Cow<'static, str>, Cow<'static, str>>,
+
+    /// Variables inherited from the launcher's environment. When set, all other
+    /// variables are removed from the environment of the plugin's process.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    env_allow: Option<Vec<Cow<'static, str>>>,
+
+    /// Limits on the resources the plugin's process may use.
+    #[serde(default)]
+    limits: PluginLimits,
+}
+
+impl PluginBinary {
+    pub fn args(&self) -> impl Iterator<Item = &str> {
+        self.args.iter().map(AsRef::as_ref)
+    }
+
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref().map(Path::new)
+    }
+
+    pub fn env(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.env
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_ref()))
+    }
+
+    pub fn env_allow(&self) -> Option<impl Iterator<Item = &str>> {
+        self.env_allow
+            .as_ref()
+            .map(|vars| vars.iter().map(AsRef::as_ref))
+    }
+
+    pub fn limits(&self) -> &PluginLimits {
+        &self.limits
+    }
+
+    pub fn path(&self) -> &Path {
+        Path::new(self.path.as_ref())
+    }
+
+    /// Expands `~` and environment variables in the binary's options, and
+    /// resolves its relative paths against the plugin's directory.
+    fn expand(&mut self, source: &Path) {
+        let resolve = |path: &str| {
+            let path = expand(path);
+            if path.starts_with('/') {
+                path
+            } else {
+                source.join(path).to_string_lossy().into_owned()
+            }
+        };
+
+        self.path = Cow::Owned(resolve(&self.path));
+        self.cwd = self.cwd.as_deref().map(resolve).map(Cow::Owned);
+
+        for arg in &mut self.args {
+            *arg = Cow::Owned(expand(arg));
+        }
+
+        for value in self.env.values_mut() {
+            *value = Cow::Owned(expand(value));
+        }
+    }
+}
+
+/// Resource limits applied to the process of an external plugin.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct PluginLimits {
+    /// Seconds of CPU time the process may use before it is terminated.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub cpu_time: Option<u64>,
+
+    /// Mebibytes of address space the process may allocate.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub memory: Option<u64>,
+
+    /// Niceness added to that of the service for the process, which is kept within
+    /// -20 to 19.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub nice: Option<i32>,
+
+    /// Number of files the process may have open.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub open_files: Option<u64>,
 }
 
-#[derive(Debug, Default, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
 pub struct PluginQuery {
     #[serde(
         default,
@@ -60,6 +185,14 @@ pub struct PluginQuery {
     )]
     pub isolate_with: Option<Cow<'static, str>>,
 
+    /// Maximum number of results from this plugin that are displayed.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub max_results: Option<usize>,
+
     #[serde(default)]
     pub no_sort: bool,
 
@@ -75,9 +208,126 @@ pub struct PluginQuery {
         with = "::serde_with::rust::unwrap_or_skip"
     )]
     pub regex: Option<Cow<'static, str>>,
+
+    /// Milliseconds to wait for the plugin to finish a search before giving up on it.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub timeout: Option<u64>,
+}
+
+/// Overrides for the config of a plugin, defined by the user in `launcher.ron`.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PluginOverlay {
+    /// Whether the plugin is loaded at all.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub enabled: Option<bool>,
+
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub help: Option<Cow<'static, str>>,
+
+    /// Seconds without requests after which the plugin's process is stopped.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub idle_timeout: Option<u64>,
+
+    /// Whether the plugin runs within the service, if it is bundled with the launcher.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub in_process: Option<bool>,
+
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub isolate: Option<bool>,
+
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub isolate_with: Option<Cow<'static, str>>,
+
+    /// Maximum number of results from this plugin that are displayed.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub max_results: Option<usize>,
+
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub persistent: Option<bool>,
+
+    /// Priority of the plugin's results, relative to other plugins.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub priority: Option<PluginPriority>,
+
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub regex: Option<Cow<'static, str>>,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, PartialOrd, Ord)]
+impl PluginConfig {
+    /// Replaces the fields of the config which the overlay defines.
+    pub fn overlay(&mut self, overlay: &PluginOverlay) {
+        let query = &mut self.query;
+
+        if let Some(help) = overlay.help.as_ref() {
+            query.help = Some(help.clone());
+        }
+
+        if let Some(idle_timeout) = overlay.idle_timeout {
+            self.idle_timeout = Some(idle_timeout);
+        }
+
+        if let Some(isolate) = overlay.isolate {
+            query.isolate = isolate;
+        }
+
+        if let Some(isolate_with) = overlay.isolate_with.as_ref() {
+            query.isolate_with = Some(isolate_with.clone());
+        }
+
+        if let Some(max_results) = overlay.max_results {
+            query.max_results = Some(max_results);
+        }
+
+        if let Some(persistent) = overlay.persistent {
+            query.persistent = persistent;
+        }
+
+        if let Some(priority) = overlay.priority {
+            query.priority = priority;
+        }
+
+        if let Some(regex) = overlay.regex.as_ref() {
+            query.regex = Some(regex.clone());
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq, PartialOrd, Ord)]
 pub enum PluginPriority {
     High = 0,
     Default = 1,
@@ -90,9 +340,10 @@ impl Default for PluginPriority {
     }
 }
 
+/// Loads the config of a plugin, as it was installed.
 pub fn load(source: &Path, config_path: &Path) -> Option<(PathBuf, PluginConfig, Option<Regex>)> {
     if let Ok(config_bytes) = std::fs::read_to_string(&config_path) {
-        let config = match ron::from_str::<PluginConfig>(&config_bytes) {
+        let mut config = match ron::from_str::<PluginConfig>(&config_bytes) {
             Ok(config) => config,
             Err(why) => {
                 tracing::error!("malformed config at {}: {}", config_path.display(), why);
@@ -100,12 +351,9 @@ pub fn load(source: &Path, config_path: &Path) -> Option<(PathBuf, PluginConfig,
             }
         };
 
-        let exec = if let Some(bin) = config.bin.as_ref() {
-            if bin.path.starts_with('/') {
-                PathBuf::from((*bin.path).to_owned())
-            } else {
-                source.join(bin.path.as_ref())
-            }
+        let exec = if let Some(bin) = config.bin.as_mut() {
+            bin.expand(source);
+            bin.path().to_path_buf()
         } else {
             tracing::error!(
                 "bin field is missing from config at {}",
@@ -127,3 +375,66 @@ pub fn load(source: &Path, config_path: &Path) -> Option<(PathBuf, PluginConfig,
 
     None
 }
+
+/// Expands a leading `~` to the home directory, and `$VAR` or `${VAR}` to the value
+/// of the environment variable. Variables which are not set expand to nothing.
+fn expand(value: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+
+    let mut rest = value;
+
+    if rest == "~" || rest.starts_with("~/") {
+        if let Some(home) = dirs::home_dir() {
+            expanded.push_str(&home.to_string_lossy());
+            rest = &rest[1..];
+        }
+    }
+
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        let (name, remaining) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", rest),
+            }
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+
+        if name.is_empty() {
+            expanded.push('$');
+        } else if let Ok(var) = std::env::var(name) {
+            expanded.push_str(&var);
+        }
+
+        rest = remaining;
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+
+    #[test]
+    fn expands_variables() {
+        std::env::set_var("POP_LAUNCHER_TEST", "value");
+        std::env::remove_var("POP_LAUNCHER_UNSET");
+
+        assert_eq!("value/x", expand("$POP_LAUNCHER_TEST/x"));
+        assert_eq!("valuex", expand("${POP_LAUNCHER_TEST}x"));
+        assert_eq!("a//b", expand("a/$POP_LAUNCHER_UNSET/b"));
+        assert_eq!("$ and $", expand("$ and $"));
+
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(home.join("x").to_str().unwrap(), expand("~/x"));
+        assert_eq!("a~", expand("a~"));
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::PluginLimits;
+use std::io;
+
+const MEBIBYTE: u64 = 1024 * 1024;
+
+#[cfg(target_env = "gnu")]
+type Resource = libc::__rlimit_resource_t;
+
+#[cfg(not(target_env = "gnu"))]
+type Resource = libc::c_int;
+
+/// Applies resource limits to the current process.
+///
+/// Called in the forked child before it executes the plugin, so only
+/// async-signal-safe functions may be used.
+pub fn apply(limits: &PluginLimits) -> io::Result<()> {
+    if let Some(seconds) = limits.cpu_time {
+        set_limit(libc::RLIMIT_CPU, seconds)?;
+    }
+
+    if let Some(mebibytes) = limits.memory {
+        set_limit(libc::RLIMIT_AS, mebibytes.saturating_mul(MEBIBYTE))?;
+    }
+
+    if let Some(files) = limits.open_files {
+        set_limit(libc::RLIMIT_NOFILE, files)?;
+    }
+
+    if let Some(nice) = limits.nice {
+        renice(nice)?;
+    }
+
+    Ok(())
+}
+
+/// Adds to the niceness of the current process, which it inherited from the service.
+///
+/// A process may not lower its niceness without privileges, so the plugin runs at
+/// the niceness that it inherited if the increment is refused.
+fn renice(increment: i32) -> io::Result<()> {
+    // Safety: errno is thread-local, and getpriority only reads its arguments. As
+    // -1 is a valid niceness, errno tells whether getpriority failed.
+    let current = unsafe {
+        *libc::__errno_location() = 0;
+        libc::getpriority(libc::PRIO_PROCESS, 0)
+    };
+
+    if current == -1 {
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(0) {
+            return Err(error);
+        }
+    }
+
+    let nice = current.saturating_add(increment).clamp(-20, 19);
+
+    // Safety: setpriority only reads its arguments.
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } == -1 {
+        let error = io::Error::last_os_error();
+        if !matches!(error.raw_os_error(), Some(libc::EPERM) | Some(libc::EACCES)) {
+            return Err(error);
+        }
+    }
+
+    Ok(())
+}
+
+fn set_limit(resource: Resource, value: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+
+    // Safety: the limit is a valid rlimit which outlives the call.
+    if unsafe { libc::setrlimit(resource, &limit) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    #[test]
+    fn limits_apply_to_child() {
+        let limits = PluginLimits {
+            cpu_time: Some(30),
+            memory: Some(512),
+            nice: Some(5),
+            open_files: Some(64),
+        };
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "ulimit -t; ulimit -v; ulimit -n; nice"]);
+
+        // Safety: the limits are applied with async-signal-safe system calls.
+        unsafe {
+            command.pre_exec(move || apply(&limits));
+        }
+
+        // Safety: getpriority only reads its arguments.
+        let parent = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        let output = command.output().unwrap();
+
+        assert_eq!(
+            format!("30\n524288\n64\n{}\n", (parent + 5).min(19)),
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+}
@@ -1,22 +1,30 @@
 // Copyright 2021 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
+mod limits;
 pub mod load;
+mod supervisor;
+pub mod watch;
 
 use std::{
+    borrow::Cow,
     io,
-    path::PathBuf,
-    process::Stdio,
+    process::{ExitStatus, Stdio},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
 };
 
-use crate::{Event, Indice, Plugin, PluginResponse, Request};
+use crate::{
+    Capabilities, Event, Indice, Plugin, PluginBinary, PluginId, PluginResponse, Query, Request,
+};
 use async_oneshot::oneshot;
-use flume::Sender;
+use flume::{Receiver, Sender};
+use futures::future::Either;
 use futures::StreamExt;
+use supervisor::Supervisor;
 use tokio::{
     io::AsyncWriteExt,
     process::{Child, Command},
@@ -24,42 +32,77 @@ use tokio::{
 };
 use tracing::{event, Level};
 
+/// Time given to a plugin to exit after being requested to, before it is killed.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Time given to a newly launched plugin to introduce itself, before its first
+/// request is sent as though it speaks the protocol of old.
+const GREETING_TIMEOUT: Duration = Duration::from_millis(100);
+
 pub struct ExternalPlugin {
-    id: usize,
+    id: PluginId,
     tx: Sender<Event>,
     name: String,
-    pub cmd: PathBuf,
-    pub args: Vec<String>,
+    pub bin: PluginBinary,
     process: Option<(JoinHandle<()>, Child, async_oneshot::Sender<()>)>,
     detached: Arc<AtomicBool>,
-    searching: Arc<AtomicBool>,
+    /// Searches sent to the running process which it has yet to finish
+    unfinished: Arc<AtomicUsize>,
+    supervisor: Supervisor,
+    /// What the running process understands, as advertised in its `Hello`
+    capabilities: Capabilities,
+    /// Receives the capabilities of the running process, until it introduces itself
+    greeting: Option<Receiver<Capabilities>>,
+    /// Whether the running process has yet to be sent a request
+    fresh: bool,
 }
 
 impl ExternalPlugin {
-    pub fn new(
-        id: usize,
-        name: String,
-        cmd: PathBuf,
-        args: Vec<String>,
-        tx: Sender<Event>,
-    ) -> Self {
+    pub fn new(id: PluginId, name: String, bin: PluginBinary, tx: Sender<Event>) -> Self {
         Self {
             id,
             name,
             tx,
-            cmd,
-            args,
+            bin,
             process: None,
             detached: Arc::default(),
-            searching: Arc::default(),
+            unfinished: Arc::default(),
+            supervisor: Supervisor::default(),
+            capabilities: Capabilities::default(),
+            greeting: None,
+            fresh: false,
         }
     }
 
     pub fn launch(&mut self) -> Option<&mut (JoinHandle<()>, Child, async_oneshot::Sender<()>)> {
         event!(Level::DEBUG, "{}: launching plugin", self.name());
 
-        let child = Command::new(&self.cmd)
-            .args(&self.args)
+        let mut command = Command::new(self.bin.path());
+
+        if let Some(allowed) = self.bin.env_allow() {
+            command.env_clear();
+
+            for var in allowed {
+                if let Some(value) = std::env::var_os(var) {
+                    command.env(var, value);
+                }
+            }
+        }
+
+        command.args(self.bin.args()).envs(self.bin.env());
+
+        if let Some(cwd) = self.bin.cwd() {
+            command.current_dir(cwd);
+        }
+
+        let limits = *self.bin.limits();
+
+        // Safety: the limits are applied with async-signal-safe system calls.
+        unsafe {
+            command.pre_exec(move || limits::apply(&limits));
+        }
+
+        let child = command
             .stdout(Stdio::piped())
             .stdin(Stdio::piped())
             .stderr(Stdio::inherit())
@@ -69,17 +112,24 @@ impl ExternalPlugin {
         if let Some(mut child) = child {
             if let Some(stdout) = child.stdout.take() {
                 let detached = self.detached.clone();
-                let searching = self.searching.clone();
                 let (trip_tx, trip_rx) = oneshot::<()>();
                 let tx = self.tx.clone();
                 let name = self.name().to_owned();
                 let id = self.id;
+                let (greeting, greeting_rx) = flume::bounded(1);
+
+                self.unfinished = Arc::default();
+                let unfinished = self.unfinished.clone();
+
+                self.capabilities = Capabilities::default();
+                self.greeting = Some(greeting_rx);
+                self.fresh = true;
 
                 // Spawn a background task to forward JSON responses from the child process.
                 let task = tokio::spawn(async move {
                     let tx_ = tx.clone();
-                    let searching_ = searching.clone();
                     let name_ = name.clone();
+                    let unfinished_ = unfinished.clone();
 
                     // Future for directly handling the JSON output from the process.
                     let responder = async move {
@@ -87,9 +137,12 @@ impl ExternalPlugin {
 
                         while let Some(result) = requests.next().await {
                             match result {
+                                Ok(PluginResponse::Hello(capabilities)) => {
+                                    let _ = greeting.try_send(capabilities);
+                                }
                                 Ok(response) => {
                                     if let PluginResponse::Finished = response {
-                                        searching_.store(false, Ordering::SeqCst);
+                                        search_finished(&unfinished_);
                                     }
 
                                     let _ = tx_.send_async(Event::Response((id, response))).await;
@@ -110,16 +163,11 @@ impl ExternalPlugin {
                     futures::pin_mut!(responder);
                     futures::pin_mut!(trip);
 
-                    let _ = futures::future::select(responder, trip)
-                        .await
-                        .factor_first()
-                        .0;
+                    let outcome = futures::future::select(responder, trip).await;
 
-                    // Ensure that a task that was searching sends a finished signal if it dies.
-                    if searching.swap(false, Ordering::SeqCst) {
-                        let _ = tx
-                            .send_async(Event::Response((id, PluginResponse::Finished)))
-                            .await;
+                    // A process which stopped on its own will not finish its searches
+                    if let Either::Left(_) = outcome {
+                        report_unfinished(&tx, id, &unfinished).await;
                     }
 
                     detached.store(true, Ordering::SeqCst);
@@ -137,8 +185,17 @@ impl ExternalPlugin {
     pub async fn process_check(&mut self) {
         if let Some(mut child) = self.process.take() {
             match child.1.try_wait() {
-                Err(_) | Ok(Some(_)) => {
+                Ok(Some(status)) => {
                     child.0.abort();
+                    report_unfinished(&self.tx, self.id, &self.unfinished).await;
+
+                    if !status.success() {
+                        self.crashed(Some(status)).await;
+                    }
+                }
+                Err(_) => {
+                    child.0.abort();
+                    report_unfinished(&self.tx, self.id, &self.unfinished).await;
                 }
                 Ok(None) => self.process = Some(child),
             }
@@ -153,10 +210,58 @@ impl ExternalPlugin {
         self.process_check().await;
 
         if self.process.is_none() {
+            if !self.supervisor.may_launch(Instant::now()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "plugin is waiting to be relaunched after crashing",
+                ));
+            }
+
             tracing::debug!("{}: relaunching process", self.name());
-            self.launch();
+
+            if self.launch().is_none() {
+                self.crashed(None).await;
+            }
+        }
+
+        // A plugin which introduced itself is answered before its next request. One
+        // which was just launched is given time to, lest its first request be downgraded.
+        let greeting = match self.greeting.as_ref() {
+            Some(greeting) if self.fresh => {
+                tokio::time::timeout(GREETING_TIMEOUT, greeting.recv_async())
+                    .await
+                    .ok()
+                    .and_then(Result::ok)
+            }
+            Some(greeting) => greeting.try_recv().ok(),
+            None => None,
+        };
+
+        self.fresh = false;
+
+        if let Some(capabilities) = greeting {
+            tracing::debug!("{}: introduced itself with {:?}", self.name(), capabilities);
+            self.greeting = None;
+            self.capabilities = capabilities;
+            self.send(&Request::Hello(Capabilities::current())).await?;
+        }
+
+        // Counted beforehand, as the process may finish before the request is sent
+        let searching = matches!(event, Request::Search(_));
+        if searching {
+            self.unfinished.fetch_add(1, Ordering::SeqCst);
         }
 
+        let result = self.send(&downgrade(event, &self.capabilities)).await;
+
+        if searching && result.is_err() {
+            search_finished(&self.unfinished);
+        }
+
+        result
+    }
+
+    async fn send(&mut self, event: &Request) -> io::Result<()> {
         if let Some((_, child, _)) = self.process.as_mut() {
             if let Some(stdin) = child.stdin.as_mut() {
                 if let Ok(mut serialized) = serde_json::to_vec(event) {
@@ -174,6 +279,46 @@ impl ExternalPlugin {
             "child process could not be reached",
         ))
     }
+
+    /// Records a crash of the plugin's process, or a failure to spawn it, and
+    /// reports the plugin as failed if it has crashed too often.
+    async fn crashed(&mut self, status: Option<ExitStatus>) {
+        match status {
+            Some(status) => tracing::error!("{}: plugin exited with {}", self.name(), status),
+            None => tracing::error!("{}: plugin failed to launch", self.name()),
+        }
+
+        let _ = self.tx.send_async(Event::PluginCrashed(self.id)).await;
+
+        if self.supervisor.crashed(Instant::now()) {
+            let reason = match status {
+                Some(_) => format!("crashed {} times", self.supervisor.crashes()),
+                None => format!("failed to launch {} times", self.supervisor.crashes()),
+            };
+
+            tracing::error!("{}: giving up on plugin, as it {}", self.name(), reason);
+
+            let _ = self
+                .tx
+                .send_async(Event::PluginFailed(self.id, reason))
+                .await;
+        }
+    }
+}
+
+/// Records that a process finished the oldest of its unfinished searches.
+fn search_finished(unfinished: &AtomicUsize) {
+    let _ = unfinished.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+}
+
+/// Tells the service how many searches a process which stopped on its own left
+/// unfinished, such that they are not awaited. Each search is reported only once.
+async fn report_unfinished(tx: &Sender<Event>, id: PluginId, unfinished: &AtomicUsize) {
+    let searches = unfinished.swap(0, Ordering::SeqCst);
+
+    if searches > 0 {
+        let _ = tx.send_async(Event::PluginExit(id, searches)).await;
+    }
 }
 
 #[async_trait::async_trait]
@@ -195,8 +340,28 @@ impl Plugin for ExternalPlugin {
     }
 
     fn exit(&mut self) {
-        if let Some((_, _, mut trigger)) = self.process.take() {
+        if let Some((_, mut child, mut trigger)) = self.process.take() {
             let _ = trigger.send(());
+
+            let name = self.name.clone();
+
+            // Asks the process to exit, and reaps it, killing it if it does not comply.
+            tokio::spawn(async move {
+                if let Some(mut stdin) = child.stdin.take() {
+                    if let Ok(mut serialized) = serde_json::to_vec(&Request::Exit) {
+                        serialized.push(b'\n');
+                        let _ = stdin.write_all(&serialized).await;
+                    }
+                }
+
+                if tokio::time::timeout(EXIT_TIMEOUT, child.wait())
+                    .await
+                    .is_err()
+                {
+                    tracing::debug!("{}: killing plugin which did not exit", name);
+                    let _ = child.kill().await;
+                }
+            });
         }
     }
 
@@ -208,10 +373,8 @@ impl Plugin for ExternalPlugin {
         &self.name
     }
 
-    async fn search(&mut self, query: &str) {
-        if self.query(&Request::Search(query.to_owned())).await.is_ok() {
-            self.searching.store(true, Ordering::SeqCst);
-        } else {
+    async fn search(&mut self, query: &Query) {
+        if self.query(&Request::Search(query.clone())).await.is_err() {
             let _ = self
                 .tx
                 .send_async(Event::Response((self.id, PluginResponse::Finished)))
@@ -222,3 +385,82 @@ impl Plugin for ExternalPlugin {
         let _ = self.query(&Request::Quit(id)).await;
     }
 }
+
+/// The request as understood by a plugin with the given capabilities. Plugins
+/// which have not introduced themselves expect searches as plain text, knowing
+/// neither of IDs nor of triggers.
+fn downgrade<'a>(request: &'a Request, capabilities: &Capabilities) -> Cow<'a, Request> {
+    match request {
+        Request::Search(query) if !capabilities.query_id => {
+            Cow::Owned(Request::Search(Query::from(query.text.clone())))
+        }
+        _ => Cow::Borrowed(request),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_plugins_search_plain_text() {
+        let search = Request::Search(Query {
+            text: String::from("ls"),
+            id: Some(3),
+            trigger: Some(String::from("t:")),
+            keep_accents: false,
+        });
+
+        let serialize =
+            |capabilities| serde_json::to_string(&downgrade(&search, &capabilities)).unwrap();
+
+        assert_eq!(r#"{"Search":"ls"}"#, serialize(Capabilities::default()));
+        assert_eq!(
+            r#"{"Search":{"text":"ls","id":3,"trigger":"t:"}}"#,
+            serialize(Capabilities::current())
+        );
+    }
+
+    #[tokio::test]
+    async fn first_search_awaits_greeting() {
+        let file =
+            std::env::temp_dir().join(format!("pop-launcher-greeting-{}", std::process::id()));
+        let script = r#"echo '{"Hello":{"version":1,"query_id":true}}'; read -r hello; read -r search; printf '%s' "$search" > "$0"; echo '"Finished"'"#;
+
+        let bin: PluginBinary = serde_json::from_value(serde_json::json!({
+            "path": "/bin/sh",
+            "args": ["-c", script, file.to_str().unwrap()],
+        }))
+        .unwrap();
+
+        let (tx, rx) = flume::unbounded();
+        let id = PluginId {
+            key: 0,
+            generation: 1,
+        };
+
+        let mut plugin = ExternalPlugin::new(id, String::from("Greeter"), bin, tx);
+
+        plugin
+            .search(&Query {
+                text: String::from("ls"),
+                id: Some(3),
+                trigger: Some(String::from(":")),
+                keep_accents: false,
+            })
+            .await;
+
+        while let Ok(event) = rx.recv_async().await {
+            if let Event::Response((_, PluginResponse::Finished)) = event {
+                break;
+            }
+        }
+
+        assert_eq!(
+            r#"{"Search":{"text":"ls","id":3,"trigger":":"}}"#,
+            std::fs::read_to_string(&file).unwrap()
+        );
+
+        let _ = std::fs::remove_file(&file);
+    }
+}
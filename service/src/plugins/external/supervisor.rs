@@ -0,0 +1,96 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Delay before relaunching a plugin after its first crash, doubling with each crash after.
+const BACKOFF_BASE: Duration = Duration::from_millis(250);
+
+/// Longest delay before relaunching a plugin which has crashed.
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Number of crashes within the window after which a plugin is considered failed.
+const MAX_CRASHES: usize = 5;
+
+/// Period of time in which crashes are counted.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Tracks the crashes of a plugin's process to decide when it may be relaunched.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    crashes: VecDeque<Instant>,
+    failed: bool,
+    relaunch_at: Option<Instant>,
+}
+
+impl Supervisor {
+    /// Records a crash, and returns `true` if the plugin has now failed.
+    pub fn crashed(&mut self, now: Instant) -> bool {
+        while let Some(&crash) = self.crashes.front() {
+            if now.duration_since(crash) < WINDOW {
+                break;
+            }
+
+            self.crashes.pop_front();
+        }
+
+        self.crashes.push_back(now);
+
+        let exponent = self.crashes.len().saturating_sub(1).min(16) as u32;
+        let backoff = (BACKOFF_BASE * 2u32.pow(exponent)).min(BACKOFF_MAX);
+        self.relaunch_at = Some(now + backoff);
+
+        if self.crashes.len() >= MAX_CRASHES && !self.failed {
+            self.failed = true;
+            return true;
+        }
+
+        false
+    }
+
+    /// Number of crashes within the current window.
+    pub fn crashes(&self) -> usize {
+        self.crashes.len()
+    }
+
+    /// Whether the plugin may be launched, as it has neither failed nor is backing off.
+    pub fn may_launch(&self, now: Instant) -> bool {
+        !self.failed && self.relaunch_at.is_none_or(|at| now >= at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_then_fails() {
+        let mut supervisor = Supervisor::default();
+        let start = Instant::now();
+
+        assert!(supervisor.may_launch(start));
+
+        assert!(!supervisor.crashed(start));
+        assert!(!supervisor.may_launch(start + Duration::from_millis(100)));
+        assert!(supervisor.may_launch(start + BACKOFF_BASE));
+
+        // Backoff doubles with each crash in the window
+        let second = start + BACKOFF_BASE;
+        assert!(!supervisor.crashed(second));
+        assert!(!supervisor.may_launch(second + BACKOFF_BASE));
+        assert!(supervisor.may_launch(second + BACKOFF_BASE * 2));
+
+        // Crashes outside of the window are forgotten
+        let later = start + WINDOW * 2;
+        assert!(!supervisor.crashed(later));
+        assert_eq!(1, supervisor.crashes());
+
+        for crash in 1..MAX_CRASHES - 1 {
+            assert!(!supervisor.crashed(later + Duration::from_secs(crash as u64)));
+        }
+
+        assert!(supervisor.crashed(later + Duration::from_secs(10)));
+        assert!(!supervisor.may_launch(later + WINDOW * 10));
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::Event;
+use flume::Sender;
+use futures::StreamExt;
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    path::Path,
+    time::Duration,
+};
+
+/// Time to wait for changes to settle before plugins are reloaded.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Watches the plugin paths with inotify, and notifies the service when plugins
+/// have been installed, removed, or had their config changed, or when the
+/// launcher's own config has changed.
+pub async fn watch(tx: Sender<Event>) {
+    let inotify = match Inotify::init() {
+        Ok(inotify) => inotify,
+        Err(why) => {
+            tracing::error!("failed to watch plugin directories: {}", why);
+            return;
+        }
+    };
+
+    let mut events = match inotify.into_event_stream([0; 4096]) {
+        Ok(events) => events,
+        Err(why) => {
+            tracing::error!("failed to watch plugin directories: {}", why);
+            return;
+        }
+    };
+
+    let mut watches = events.watches();
+    let mut watched = Watched::default();
+    watched.add(&mut watches, crate::paths(), crate::plugin_paths());
+
+    while let Some(event) = events.next().await {
+        let event = match event {
+            Ok(event) => event,
+            Err(why) => {
+                tracing::error!("failed to read plugin directory changes: {}", why);
+                break;
+            }
+        };
+
+        if !watched.concerns(&event) {
+            continue;
+        }
+
+        // Waits for a burst of changes, such as a package install, to end
+        while let Ok(Some(_)) = tokio::time::timeout(DEBOUNCE, events.next()).await {}
+
+        // Directories created since watches were last added are watched too
+        watched.add(&mut watches, crate::paths(), crate::plugin_paths());
+
+        if tx.send_async(Event::PluginsChanged).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// The directories being watched, and which of the changes within them concern
+/// the launcher.
+#[derive(Default)]
+struct Watched {
+    /// Directories of the launcher, whose every change concerns it
+    dirs: HashSet<WatchDescriptor>,
+
+    /// Ancestors of plugin paths which do not exist yet, with the names of the
+    /// directories within them which lead to those paths
+    ancestors: HashMap<WatchDescriptor, HashSet<OsString>>,
+}
+
+impl Watched {
+    /// Watches each plugin path, the config of every plugin within them, and the
+    /// directories which `launcher.ron` is read from.
+    ///
+    /// A plugin path which does not exist yet is watched through its nearest
+    /// existing ancestor, so that plugins will be found once it has been created.
+    fn add<P: AsRef<Path>>(
+        &mut self,
+        watches: &mut Watches,
+        paths: impl Iterator<Item = P>,
+        plugin_paths: impl Iterator<Item = P>,
+    ) {
+        let mask = WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO
+            | WatchMask::CLOSE_WRITE;
+
+        *self = Self::default();
+
+        let mut watch = |path: &Path| watches.add(path, mask).ok();
+
+        for path in paths {
+            let path = path.as_ref();
+            if path.exists() {
+                self.dirs.extend(watch(path));
+            }
+        }
+
+        for path in plugin_paths {
+            let path = path.as_ref();
+
+            if let Some((ancestor, name)) = nearest_ancestor(path) {
+                if let Some(wd) = watch(ancestor) {
+                    self.ancestors
+                        .entry(wd)
+                        .or_default()
+                        .insert(name.to_owned());
+                }
+            } else if path.exists() {
+                self.dirs.extend(watch(path));
+            }
+
+            if let Ok(readdir) = path.read_dir() {
+                for entry in readdir.filter_map(Result::ok) {
+                    if entry.path().is_dir() {
+                        self.dirs.extend(watch(&entry.path()));
+                    }
+                }
+            }
+        }
+
+        let dirs = &self.dirs;
+        self.ancestors.retain(|wd, _| !dirs.contains(wd));
+    }
+
+    /// Whether the change may have installed, removed, or changed a plugin or the
+    /// launcher's config, rather than being an unrelated change within an ancestor.
+    fn concerns(&self, event: &inotify::Event<OsString>) -> bool {
+        if self.dirs.contains(&event.wd) || event.mask.contains(EventMask::Q_OVERFLOW) {
+            return true;
+        }
+
+        match (self.ancestors.get(&event.wd), event.name.as_ref()) {
+            (Some(names), Some(name)) => names.contains(name),
+            _ => false,
+        }
+    }
+}
+
+/// The nearest existing ancestor of a path which does not exist, with the name of
+/// the directory within it which leads to the path.
+fn nearest_ancestor(path: &Path) -> Option<(&Path, &OsStr)> {
+    if path.exists() {
+        return None;
+    }
+
+    let mut child = path;
+
+    for ancestor in path.ancestors().skip(1) {
+        if ancestor.exists() {
+            return child.file_name().map(|name| (ancestor, name));
+        }
+
+        child = ancestor;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inotify::EventStream;
+
+    /// Whether any of the changes since last checked concern the launcher.
+    async fn changed(events: &mut EventStream<[u8; 1024]>, watched: &Watched) -> bool {
+        let mut concerns = false;
+
+        let timeout = Duration::from_millis(100);
+        while let Ok(Some(Ok(event))) = tokio::time::timeout(timeout, events.next()).await {
+            concerns |= watched.concerns(&event);
+        }
+
+        concerns
+    }
+
+    #[tokio::test]
+    async fn ignores_unrelated_changes_of_ancestors() {
+        let root = std::env::temp_dir().join(format!("pop-launcher-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("share")).unwrap();
+
+        let launcher = root.join("share/pop-launcher");
+        let plugins = launcher.join("plugins");
+
+        let mut events = Inotify::init()
+            .unwrap()
+            .into_event_stream([0; 1024])
+            .unwrap();
+        let mut watches = events.watches();
+        let mut watched = Watched::default();
+
+        let mut add = |watched: &mut Watched| {
+            watched.add(&mut watches, [&launcher].iter(), [&plugins].iter());
+        };
+
+        add(&mut watched);
+
+        std::fs::write(root.join("share/unrelated"), "").unwrap();
+        assert!(!changed(&mut events, &watched).await);
+
+        std::fs::create_dir(&launcher).unwrap();
+        assert!(changed(&mut events, &watched).await);
+        add(&mut watched);
+
+        std::fs::write(launcher.join("launcher.ron"), "()").unwrap();
+        assert!(changed(&mut events, &watched).await);
+
+        std::fs::write(root.join("share/unrelated"), "").unwrap();
+        assert!(!changed(&mut events, &watched).await);
+
+        std::fs::create_dir(&plugins).unwrap();
+        assert!(changed(&mut events, &watched).await);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
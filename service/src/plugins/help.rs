@@ -7,7 +7,7 @@ use pop_launcher::*;
 use slab::Slab;
 use std::borrow::Cow;
 
-pub const REGEX: Cow<'static, str> = Cow::Borrowed("^(\\?).*");
+pub const REGEX: Cow<'static, str> = Cow::Borrowed("^\\?\\s*(?P<query>.*)");
 
 pub const CONFIG: PluginConfig = PluginConfig {
     name: Cow::Borrowed("Help"),
@@ -17,22 +17,47 @@ pub const CONFIG: PluginConfig = PluginConfig {
         help: None,
         isolate: true,
         isolate_with: None,
+        max_results: None,
         no_sort: true,
         persistent: false,
         priority: PluginPriority::Default,
         regex: None,
+        timeout: None,
     },
     icon: Some(IconSource::Name(Cow::Borrowed("system-help-symbolic"))),
-    history: false
+    history: false,
+    idle_timeout: None,
 };
 pub struct HelpPlugin {
-    pub id: usize,
+    pub id: PluginId,
     pub details: Slab<PluginHelp>,
     pub tx: Sender<Event>,
 }
 
+impl PluginHelp {
+    /// Whether the plugin is listed by the help plugin.
+    fn listed(&self) -> bool {
+        self.help.is_some() || self.failure.is_some()
+    }
+
+    /// Whether every word of the query is found in the name, description, or
+    /// prefix of the plugin.
+    fn matches(&self, query: &str) -> bool {
+        let haystack = normalize::fold(&format!(
+            "{} {} {}",
+            self.name,
+            self.description,
+            self.help.as_deref().unwrap_or_default()
+        ));
+
+        normalize::fold(query)
+            .split_whitespace()
+            .all(|word| haystack.contains(word))
+    }
+}
+
 impl HelpPlugin {
-    pub fn new(id: usize, tx: Sender<Event>) -> Self {
+    pub fn new(id: PluginId, tx: Sender<Event>) -> Self {
         Self {
             id,
             details: Slab::new(),
@@ -45,6 +70,13 @@ impl HelpPlugin {
         let _ = self.tx.send_async(Event::Help(tx)).await;
         self.details = rx.await.expect("internal error fetching help info");
     }
+
+    async fn respond(&self, response: PluginResponse) {
+        let _ = self
+            .tx
+            .send_async(Event::Response((self.id, response)))
+            .await;
+    }
 }
 
 #[async_trait::async_trait]
@@ -52,24 +84,45 @@ impl Plugin for HelpPlugin {
     async fn activate(&mut self, id: u32) {
         if let Some(detail) = self.details.get(id as usize) {
             if let Some(help) = detail.help.as_ref() {
-                let _ = self
-                    .tx
-                    .send_async(Event::Response((
-                        self.id,
-                        PluginResponse::Fill(help.clone()),
-                    )))
-                    .await;
+                self.respond(PluginResponse::Fill(help.clone())).await;
             }
         }
     }
 
-    async fn activate_context(&mut self, _: u32, _: u32) {}
+    // The options of a plugin only describe it, so any of them fills its prefix
+    async fn activate_context(&mut self, id: u32, _: u32) {
+        self.activate(id).await
+    }
 
     async fn complete(&mut self, id: u32) {
         self.activate(id).await
     }
 
-    async fn context(&mut self, _: u32) {}
+    /// Describes the plugin in full, with a line of its description per option,
+    /// followed by the pattern which queries must match to reach it.
+    async fn context(&mut self, id: u32) {
+        let detail = match self.details.get(id as usize) {
+            Some(detail) => detail,
+            None => return,
+        };
+
+        let lines = detail.description.lines().map(String::from);
+        let regex = detail
+            .regex
+            .as_ref()
+            .map(|regex| ["Pattern: ", regex].concat());
+
+        let options = lines
+            .chain(regex)
+            .enumerate()
+            .map(|(id, name)| ContextOption {
+                id: id as u32,
+                name,
+            })
+            .collect();
+
+        self.respond(PluginResponse::Context { id, options }).await;
+    }
 
     fn exit(&mut self) {}
 
@@ -79,31 +132,53 @@ impl Plugin for HelpPlugin {
         "help"
     }
 
-    async fn search(&mut self, _query: &str) {
-        if self.details.is_empty() {
-            self.reload().await;
-        }
+    async fn search(&mut self, query: &Query) {
+        // Plugins may have been loaded, unloaded, or failed since the last search
+        self.reload().await;
+
         for (id, detail) in self.details.iter() {
-            if detail.help.is_some() {
-                let response = PluginResponse::Append(PluginSearchResult {
-                    id: id as u32,
-                    name: detail.name.clone(),
-                    description: detail.description.clone(),
-                    ..Default::default()
-                });
-
-                let _ = self
-                    .tx
-                    .send_async(Event::Response((self.id, response)))
-                    .await;
+            if !detail.listed() || !detail.matches(query) {
+                continue;
             }
+
+            let description = match detail.failure.as_ref() {
+                Some(reason) => ["Failed: ", reason].concat(),
+                None => detail.description.clone(),
+            };
+
+            let response = PluginResponse::Append(PluginSearchResult {
+                id: id as u32,
+                name: detail.name.clone(),
+                description,
+                ..Default::default()
+            });
+
+            self.respond(response).await;
         }
 
-        let _ = self
-            .tx
-            .send_async(Event::Response((self.id, PluginResponse::Finished)))
-            .await;
+        self.respond(PluginResponse::Finished).await;
     }
 
     async fn quit(&mut self, _id: u32) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_every_word_of_query() {
+        let detail = PluginHelp {
+            name: String::from("Calculator"),
+            description: String::from("Syntax: = <expression>\nExample: = 2 + 2"),
+            help: Some(String::from("= ")),
+            failure: None,
+            regex: None,
+        };
+
+        assert!(detail.matches(""));
+        assert!(detail.matches("calc"));
+        assert!(detail.matches("CALC expression"));
+        assert!(!detail.matches("calc files"));
+    }
+}
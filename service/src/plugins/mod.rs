@@ -1,20 +1,30 @@
 // Copyright 2021 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod builtin;
 pub(crate) mod external;
 pub mod config;
 pub mod help;
 
 pub use external::load;
 
-pub use self::config::{PluginBinary, PluginConfig, PluginPriority, PluginQuery};
+pub use self::builtin::BuiltinPlugin;
+pub use self::config::{
+    PluginBinary, PluginConfig, PluginLimits, PluginOverlay, PluginPriority, PluginQuery,
+};
 pub use self::external::ExternalPlugin;
 pub use self::help::HelpPlugin;
 
-use crate::{Indice, PluginHelp, Request};
+use crate::session::SessionKey;
+use crate::{Indice, PluginHelp, PluginStats, Query, Request};
 use async_trait::async_trait;
 use flume::{Receiver, Sender};
 use regex::Regex;
+use std::{collections::VecDeque, path::PathBuf, time::Duration};
+use tokio::time::Instant;
+
+/// Number of searches a plugin may fail to finish in time before it is disabled.
+const MAX_SEARCH_MISSES: u32 = 3;
 
 #[async_trait]
 pub trait Plugin
@@ -36,7 +46,7 @@ where
 
     fn name(&self) -> &str;
 
-    async fn search(&mut self, query: &str);
+    async fn search(&mut self, query: &Query);
 
     async fn quit(&mut self, id: Indice);
 
@@ -62,6 +72,10 @@ where
                     self.exit();
                     break;
                 }
+                // Only plugins in their own process introduce themselves
+                Request::Hello(_) => (),
+                // Only the service keeps stats, results, and queries
+                Request::History { .. } | Request::Page { .. } | Request::Stats => (),
             }
         }
 
@@ -76,24 +90,69 @@ pub struct PluginConnector {
     /// The deserialized configuration file for this plugin
     pub config: PluginConfig,
 
+    /// The configuration as it was loaded, before the user's overrides were applied
+    pub loaded: PluginConfig,
+
+    /// Set when the plugin has repeatedly failed to finish its searches in time,
+    /// after which it will no longer receive queries
+    pub disabled: bool,
+
+    /// The executable of an external plugin, which is reloaded if it changes
+    pub exec: Option<PathBuf>,
+
+    /// Whether the plugin runs within the service, rather than in a process of its own
+    pub in_process: bool,
+
+    /// Why the plugin was disabled, if it was
+    pub failure: Option<String>,
+
+    /// Generation of the plugin, which its responses are tagged with
+    pub generation: u64,
+
     /// Code that is executed to prepare a new instance of
     /// this plugin to spawn as a background service
     pub init: Box<dyn Fn() -> Sender<Request>>,
 
     pub isolate_regex: Option<Regex>,
 
+    /// When the plugin was last sent a request, if it is running
+    pub last_request: Option<Instant>,
+
+    /// Health score of the plugin, which rises with each search that timed out,
+    /// and falls with each search that finished in time
+    pub misses: u32,
+
     /// A compiled regular expression that a query must match
     /// for the launcher service to justify spawning and sending
     /// queries to this plugin
     pub regex: Option<Regex>,
 
+    /// The session which last sent a request other than a search to this plugin,
+    /// which its responses to such requests are sent to
+    pub requester: Option<SessionKey>,
+
+    /// The session whose search results this plugin is holding
+    pub searched_for: Option<SessionKey>,
+
+    /// Sessions which sent searches that this plugin has yet to finish, in the
+    /// order they were sent. Results of searches marked `None` are discarded.
+    pub searches: VecDeque<Option<SessionKey>>,
+
     /// The sender of the spawned background service that will be
     /// forwarded to the launncher service
     pub sender: Option<Sender<Request>>,
+
+    /// When each unfinished search was sent, and whether the plugin has since
+    /// appended a result for it, in the same order as `searches`
+    pub sent: VecDeque<(Instant, bool)>,
+
+    /// Latency and health of the plugin since it was loaded
+    pub stats: PluginStats,
 }
 
 impl PluginConnector {
     pub fn new(
+        loaded: PluginConfig,
         config: PluginConfig,
         regex: Option<Regex>,
         isolate_regex: Option<Regex>,
@@ -101,10 +160,23 @@ impl PluginConnector {
     ) -> Self {
         Self {
             config,
+            disabled: false,
+            exec: None,
+            failure: None,
+            generation: 0,
+            in_process: false,
             init,
             isolate_regex,
+            last_request: None,
+            loaded,
+            misses: 0,
             regex,
+            requester: None,
+            searched_for: None,
+            searches: VecDeque::new(),
             sender: None,
+            sent: VecDeque::new(),
+            stats: PluginStats::default(),
         }
     }
 
@@ -118,6 +190,8 @@ impl PluginConnector {
                 .help
                 .as_ref()
                 .map(|x| x.as_ref().to_owned()),
+            failure: self.failure.clone(),
+            regex: self.regex.as_ref().map(|regex| regex.as_str().to_owned()),
         }
     }
 
@@ -127,15 +201,150 @@ impl PluginConnector {
     pub fn sender_exec(&mut self) -> &mut Sender<Request> {
         let &mut Self {
             ref mut sender,
+            ref mut last_request,
             ref init,
             ..
         } = self;
 
+        *last_request = Some(Instant::now());
+
         sender.get_or_insert_with(init)
     }
 
+    /// When the plugin will have been idle for long enough to be stopped, if it is
+    /// running, has an idle timeout, and has no searches in progress.
+    pub fn idle_deadline(&self) -> Option<Instant> {
+        if self.sender.is_none() || !self.searches.is_empty() {
+            return None;
+        }
+
+        let timeout = Duration::from_secs(self.config.idle_timeout?);
+        self.last_request.map(|last| last + timeout)
+    }
+
+    /// The query as it is forwarded to this plugin.
+    ///
+    /// If the pattern which matched the query has a `query` group, only the text of
+    /// that group is forwarded, along with the prefix before it as the trigger.
+    pub fn payload(&self, query: &Query) -> Query {
+        let payload = self
+            .isolate_regex
+            .iter()
+            .chain(self.regex.iter())
+            .filter_map(|regex| regex.captures(&query.text))
+            .find_map(|captures| captures.name("query"));
+
+        match payload {
+            Some(payload) => Query {
+                text: payload.as_str().to_owned(),
+                id: query.id,
+                trigger: Some(query.text[..payload.start()].trim_end().to_owned()),
+                keep_accents: query.keep_accents,
+            },
+            None => query.clone(),
+        }
+    }
+
+    /// Records that a search was sent to the plugin on behalf of a session.
+    pub fn searching_for(&mut self, session: SessionKey) {
+        self.search_sent(Some(session));
+        self.searched_for = Some(session);
+    }
+
+    /// Records that a search was sent to the plugin, whose results are for the
+    /// session given, or discarded if `None`.
+    pub fn search_sent(&mut self, searcher: Option<SessionKey>) {
+        self.searches.push_back(searcher);
+        self.sent.push_back((Instant::now(), false));
+        self.stats.searches += 1;
+    }
+
+    /// Records that the plugin appended a result to its oldest unfinished search.
+    pub fn appended(&mut self) {
+        if let Some((sent, appended)) = self.sent.front_mut() {
+            if !*appended {
+                *appended = true;
+                self.stats.first_result.record(sent.elapsed());
+            }
+        }
+    }
+
+    /// Records that the plugin finished its oldest unfinished search, and returns
+    /// the session which the search was for.
+    pub fn search_ended(&mut self) -> Option<SessionKey> {
+        if let Some((sent, _)) = self.sent.pop_front() {
+            let elapsed = sent.elapsed();
+            tracing::debug!("{}: finished search in {:?}", self.config.name, elapsed);
+            self.stats.finished.record(elapsed);
+        }
+
+        self.searches.pop_front().flatten()
+    }
+
+    /// The latency and health of the plugin since it was loaded.
+    pub fn stats(&self) -> PluginStats {
+        PluginStats {
+            plugin: self.config.name.as_ref().to_owned(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Discards the results of the oldest unfinished search of a session.
+    pub fn abandon(&mut self, session: SessionKey) {
+        if let Some(searcher) = self.searches.iter_mut().find(|s| **s == Some(session)) {
+            *searcher = None;
+        }
+    }
+
+    /// Gives up on the oldest unfinished searches, as the plugin will not finish
+    /// them, and returns the sessions which the searches were for.
+    pub fn abandon_oldest(&mut self, count: usize) -> Vec<SessionKey> {
+        let count = count.min(self.searches.len());
+        self.sent.drain(..count.min(self.sent.len()));
+        self.searches.drain(..count).flatten().collect()
+    }
+
+    /// Discards every unfinished search of a session which has ended.
+    pub fn forget(&mut self, session: SessionKey) {
+        for searcher in self.searches.iter_mut() {
+            if *searcher == Some(session) {
+                *searcher = None;
+            }
+        }
+
+        if self.requester == Some(session) {
+            self.requester = None;
+        }
+
+        if self.searched_for == Some(session) {
+            self.searched_for = None;
+        }
+    }
+
+    /// Records that the plugin finished its search in time.
+    pub fn search_finished(&mut self) {
+        self.misses = self.misses.saturating_sub(1);
+    }
+
+    /// Records that the plugin failed to finish its search in time, and
+    /// disables the plugin if this has happened too often.
+    pub fn search_timed_out(&mut self) {
+        self.stats.timeouts += 1;
+        self.misses += 1;
+        if self.misses >= MAX_SEARCH_MISSES {
+            self.fail(String::from("stopped responding to searches"));
+        }
+    }
+
+    /// Disables the plugin, such that it will no longer receive queries.
+    pub fn fail(&mut self, reason: String) {
+        self.disabled = true;
+        self.failure = Some(reason);
+    }
+
     /// Drops the sender, which will subsequently drop the plugin forwarder attached to it
     pub fn sender_drop(&mut self) {
+        self.last_request = None;
         self.sender = None;
     }
 }
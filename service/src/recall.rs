@@ -0,0 +1,166 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::persist::Persist;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Number of queries remembered for each prefix, beyond which the oldest are forgotten.
+const MAX_QUERIES_PER_PREFIX: usize = 50;
+
+/// A query which led to the activation of a result.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Recalled {
+    /// The prefix of the plugin whose result was activated, or empty if it has none.
+    pub prefix: String,
+    pub query: String,
+}
+
+/// Queries which led to activations, from oldest to newest, which frontends may
+/// recall as a shell recalls its commands.
+#[derive(Debug, Default)]
+pub struct Recall {
+    path: Option<PathBuf>,
+    persist: Persist,
+    queries: Vec<Recalled>,
+}
+
+impl Recall {
+    /// Loads the recalled queries from the XDG state directory.
+    pub fn load() -> Self {
+        let path = match recall_path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+
+        let queries = match std::fs::read_to_string(&path) {
+            Ok(string) => match ron::from_str(&string) {
+                Ok(queries) => queries,
+                Err(why) => {
+                    tracing::error!("malformed query history at {}: {}", path.display(), why);
+                    Vec::new()
+                }
+            },
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            path: Some(path),
+            persist: Persist::default(),
+            queries,
+        }
+    }
+
+    /// Remembers a query as the newest, and persists the queries to disk.
+    pub fn record(&mut self, prefix: &str, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+
+        self.queries.retain(|recalled| recalled.query != query);
+
+        self.queries.push(Recalled {
+            prefix: prefix.to_owned(),
+            query: query.to_owned(),
+        });
+
+        let same_prefix = self
+            .queries
+            .iter()
+            .filter(|recalled| recalled.prefix == prefix)
+            .count();
+
+        if same_prefix > MAX_QUERIES_PER_PREFIX {
+            if let Some(oldest) = self.queries.iter().position(|r| r.prefix == prefix) {
+                self.queries.remove(oldest);
+            }
+        }
+
+        self.save();
+    }
+
+    /// Queries which may be recalled in place of the text typed, from newest to oldest.
+    ///
+    /// If the text begins with the prefix of a plugin, only queries for that plugin
+    /// are recalled. Otherwise, every query is.
+    pub fn matching(&self, typed: &str) -> Vec<&str> {
+        let prefix = self
+            .queries
+            .iter()
+            .map(|recalled| recalled.prefix.as_str())
+            .filter(|prefix| !prefix.is_empty() && begins_with(typed, prefix))
+            .max_by_key(|prefix| prefix.len());
+
+        self.queries
+            .iter()
+            .rev()
+            .filter(|recalled| prefix.is_none_or(|prefix| recalled.prefix == prefix))
+            .map(|recalled| recalled.query.as_str())
+            .collect()
+    }
+
+    fn save(&self) {
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return,
+        };
+
+        match ron::to_string(&self.queries) {
+            Ok(string) => self.persist.write(path.clone(), string),
+            Err(why) => tracing::error!("failed to serialize query history: {}", why),
+        }
+    }
+}
+
+/// Whether the text begins with the prefix, ending at a word boundary, such that
+/// `run` begins `run htop` but not `running`.
+fn begins_with(text: &str, prefix: &str) -> bool {
+    let rest = match text.strip_prefix(prefix) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let alphanumeric = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+
+    !(alphanumeric(prefix.chars().last()) && alphanumeric(rest.chars().next()))
+}
+
+fn recall_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/state")))
+        .map(|dir| dir.join("pop-launcher/queries.ron"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recalls_newest_first_within_prefix() {
+        let mut recall = Recall::default();
+
+        recall.record("run", "run apt update");
+        recall.record("=", "= 5 USD to EUR");
+        recall.record("", "firefox");
+        recall.record("run", "run htop");
+        recall.record("run", "run apt update");
+
+        assert_eq!(
+            vec!["run apt update", "run htop", "firefox", "= 5 USD to EUR"],
+            recall.matching("")
+        );
+
+        assert_eq!(vec!["run apt update", "run htop"], recall.matching("run "));
+        assert_eq!(vec!["= 5 USD to EUR"], recall.matching("="));
+        assert_eq!(vec!["= 5 USD to EUR"], recall.matching("=5"));
+        assert_eq!(4, recall.matching("running").len());
+
+        for n in 0..MAX_QUERIES_PER_PREFIX {
+            recall.record("=", &format!("= {}", n));
+        }
+
+        assert_eq!(MAX_QUERIES_PER_PREFIX, recall.matching("=").len());
+        assert_eq!(2, recall.matching("run").len());
+    }
+}
@@ -0,0 +1,335 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Records the messages passing through the service, and replays them against
+//! stub plugins to reproduce the responses of the service.
+
+use crate::plugins::Plugin;
+use crate::session::SessionKey;
+use crate::settings::Settings;
+use crate::{Event, PluginConfig, PluginKey, Service};
+use flume::Receiver;
+use futures::Future;
+use pop_launcher::{Indice, PluginResponse, Query, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// A message passing through the service, or a change of its state.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Entry {
+    /// The service's settings were loaded.
+    Settings(Settings),
+    /// A plugin was loaded, matching queries with the regex given.
+    Loaded {
+        plugin: PluginKey,
+        config: Box<PluginConfig>,
+        regex: Option<String>,
+    },
+    /// A plugin was unloaded.
+    Unloaded { plugin: PluginKey },
+    /// A frontend connected.
+    Connect { session: SessionKey },
+    /// A frontend disconnected.
+    Disconnect { session: SessionKey },
+    /// A frontend sent a request.
+    Request {
+        session: SessionKey,
+        request: Request,
+    },
+    /// A plugin sent a response.
+    Plugin {
+        plugin: PluginKey,
+        response: PluginResponse,
+    },
+    /// A plugin stopped on its own, leaving its oldest searches unfinished.
+    PluginExit { plugin: PluginKey, searches: usize },
+    /// A plugin's process exited with an error, or could not be launched.
+    PluginCrashed { plugin: PluginKey },
+    /// A plugin was given up on.
+    PluginFailed { plugin: PluginKey, reason: String },
+    /// The service sent a response to a frontend.
+    Response {
+        session: SessionKey,
+        response: Response,
+    },
+}
+
+/// An entry of a recording, and when it happened.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Record {
+    /// Milliseconds since the recording began.
+    pub time: u64,
+    #[serde(flatten)]
+    pub entry: Entry,
+}
+
+/// Timestamps the entries given to it by the service.
+pub struct Recorder {
+    start: Instant,
+    tx: flume::Sender<Record>,
+}
+
+impl Recorder {
+    /// Creates a recorder, whose records are received in the order they were made.
+    pub fn new() -> (Self, Receiver<Record>) {
+        let (tx, rx) = flume::unbounded();
+        let recorder = Self {
+            start: Instant::now(),
+            tx,
+        };
+
+        (recorder, rx)
+    }
+
+    pub fn record(&self, entry: Entry) {
+        let time = self.start.elapsed().as_millis() as u64;
+        let _ = self.tx.send(Record { time, entry });
+    }
+}
+
+/// The path given with `--record`, if the service was asked to record itself.
+pub fn requested() -> Option<PathBuf> {
+    std::env::args()
+        .skip_while(|arg| arg != "--record")
+        .nth(1)
+        .map(PathBuf::from)
+}
+
+/// Records the service to the path given with `--record`, if any. The future
+/// returned writes the recording, and completes once the service is dropped.
+pub fn attach<O: futures::Sink<Response> + Unpin>(
+    service: Service<O>,
+) -> (Service<O>, impl Future<Output = ()>) {
+    let mut writer = None;
+
+    let service = match requested() {
+        Some(path) => match to_file(&path) {
+            Ok((recorder, write)) => {
+                tracing::info!("recording to {}", path.display());
+                writer = Some(write);
+                service.recorded(recorder)
+            }
+            Err(why) => {
+                tracing::error!("failed to record to {}: {}", path.display(), why);
+                service
+            }
+        },
+        None => service,
+    };
+
+    let recording = async move {
+        if let Some(writer) = writer {
+            writer.await;
+        }
+    };
+
+    (service, recording)
+}
+
+/// Creates a recorder whose records are written to a file, as lines of JSON, by the
+/// future returned alongside it. The future completes once the recorder is dropped.
+pub fn to_file(path: &Path) -> io::Result<(Recorder, impl Future<Output = ()>)> {
+    let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+    let (recorder, rx) = Recorder::new();
+
+    let writer = async move {
+        while let Ok(record) = rx.recv_async().await {
+            if let Ok(mut line) = serde_json::to_vec(&record) {
+                line.push(b'\n');
+                if let Err(why) = file.write_all(&line).and_then(|_| file.flush()) {
+                    tracing::error!("failed to write recording: {}", why);
+                    return;
+                }
+            }
+        }
+    };
+
+    Ok((recorder, writer))
+}
+
+/// Reads a recording written by the service.
+pub fn read(input: impl BufRead) -> io::Result<Vec<Record>> {
+    input
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| {
+            serde_json::from_str(&line?)
+                .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))
+        })
+        .collect()
+}
+
+/// The responses that the service sent while the recording was made.
+pub fn responses(records: &[Record]) -> Vec<(SessionKey, Response)> {
+    records
+        .iter()
+        .filter_map(|record| match &record.entry {
+            Entry::Response { session, response } => Some((*session, response.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Feeds a recording to a service whose plugins are stubs, and returns the
+/// responses that the service sent.
+///
+/// Entries are fed at the time they were recorded, such that deadlines expire as
+/// they did during the recording. Pausing tokio's clock replays them instantly.
+pub async fn replay(records: &[Record]) -> Vec<(SessionKey, Response)> {
+    let (recorder, recorded) = Recorder::new();
+    let mut service = Service::new(futures::sink::drain()).recorded(recorder);
+
+    // Stub plugins never send events, and responses are read from the recorder
+    let (service_tx, _service_rx) = flume::bounded(1);
+    let (output_tx, _output_rx) = flume::unbounded();
+
+    let start = Instant::now();
+
+    for record in records {
+        let at = start + Duration::from_millis(record.time);
+
+        while let Some(deadline) = service.deadline().filter(|deadline| *deadline <= at) {
+            tokio::time::sleep_until(deadline).await;
+            service.deadline_reached().await;
+        }
+
+        tokio::time::sleep_until(at).await;
+
+        let event = match record.entry.clone() {
+            Entry::Settings(settings) => {
+                service.scorer = settings.scorer.scorer(settings.accent_insensitive);
+                service.settings = settings;
+                continue;
+            }
+
+            Entry::Loaded { config, regex, .. } => {
+                let regex = regex.and_then(|regex| regex::Regex::new(&regex).ok());
+                let name = config.name.to_string();
+                service.register_plugin(service_tx.clone(), *config, regex, move |_, _| {
+                    StubPlugin { name: name.clone() }
+                });
+                continue;
+            }
+
+            Entry::Unloaded { plugin } => {
+                for session in service.unregister_plugin(plugin).await {
+                    service.search_progressed(session).await;
+                }
+                continue;
+            }
+
+            Entry::Connect { .. } => {
+                service.connect(Some(output_tx.clone()));
+                continue;
+            }
+
+            Entry::Disconnect { session } => Event::Disconnect(session),
+            Entry::Request { session, request } => Event::Request(session, request),
+            Entry::Plugin { plugin, response } => {
+                Event::Response((service.plugin_id(plugin), response))
+            }
+            Entry::PluginExit { plugin, searches } => {
+                Event::PluginExit(service.plugin_id(plugin), searches)
+            }
+            Entry::PluginCrashed { plugin } => Event::PluginCrashed(service.plugin_id(plugin)),
+            Entry::PluginFailed { plugin, reason } => {
+                Event::PluginFailed(service.plugin_id(plugin), reason)
+            }
+
+            // What the service sent is what is being reproduced
+            Entry::Response { .. } => continue,
+        };
+
+        if !service.handle(event).await {
+            break;
+        }
+    }
+
+    drop(service);
+
+    responses(&recorded.drain().collect::<Vec<_>>())
+}
+
+/// Stands in for a plugin whose responses are fed from a recording.
+struct StubPlugin {
+    name: String,
+}
+
+#[async_trait::async_trait]
+impl Plugin for StubPlugin {
+    async fn activate(&mut self, _: Indice) {}
+
+    async fn activate_context(&mut self, _: Indice, _: Indice) {}
+
+    async fn complete(&mut self, _: Indice) {}
+
+    async fn context(&mut self, _: Indice) {}
+
+    fn exit(&mut self) {}
+
+    async fn interrupt(&mut self) {}
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&mut self, _: &Query) {}
+
+    async fn quit(&mut self, _: Indice) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn assert_replays(recording: &str) {
+        let records = read(recording.as_bytes()).expect("malformed recording");
+
+        let json = |responses: Vec<(SessionKey, Response)>| {
+            responses
+                .iter()
+                .map(|response| serde_json::to_string(response).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(json(responses(&records)), json(replay(&records).await));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scheduled_search_waits_for_plugins() {
+        assert_replays(include_str!("../tests/recordings/scheduled_search.jsonl")).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timed_out_search_is_answered() {
+        assert_replays(include_str!("../tests/recordings/timed_out_search.jsonl")).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn plugin_exit_abandons_searches() {
+        assert_replays(include_str!("../tests/recordings/plugin_exit.jsonl")).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hints_fit_within_limit() {
+        assert_replays(include_str!("../tests/recordings/alias_hints.jsonl")).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn responses_follow_capabilities() {
+        assert_replays(include_str!("../tests/recordings/capabilities.jsonl")).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pages_follow_update() {
+        assert_replays(include_str!("../tests/recordings/paged_results.jsonl")).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pages_count_alias_hints() {
+        assert_replays(include_str!("../tests/recordings/paged_hints.jsonl")).await;
+    }
+}
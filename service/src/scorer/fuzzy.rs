@@ -0,0 +1,262 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use super::Scorer;
+use pop_launcher::{normalize, PluginSearchResult};
+
+const SCORE_MATCH: i64 = 16;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_NON_WORD: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+/// Ranks results by how well the query matches as a subsequence of their fields,
+/// in the manner of fzf and skim.
+pub struct FuzzyScorer {
+    /// Strips diacritics along with case, such that `ecran` matches `Écran`.
+    pub accent_insensitive: bool,
+}
+
+impl Default for FuzzyScorer {
+    fn default() -> Self {
+        Self {
+            accent_insensitive: true,
+        }
+    }
+}
+
+impl Scorer for FuzzyScorer {
+    fn score(&self, query: &str, meta: &PluginSearchResult) -> f64 {
+        let weight = |text: &str| {
+            fuzzy_match_with(query, text, self.accent_insensitive).map_or(0.0, |m| m.weight)
+        };
+
+        let keywords = meta.keywords.as_ref().map_or(0.0, |keywords| {
+            keywords
+                .iter()
+                .fold(0.0, |acc: f64, keyword| acc.max(weight(keyword)))
+        });
+
+        weight(&meta.name)
+            .max(meta.exec.as_deref().map_or(0.0, weight))
+            .max(weight(&meta.description) - 0.1)
+            .max(keywords - 0.1)
+    }
+}
+
+/// A subsequence match of a pattern within some text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// Raw score of the match, where higher values are better.
+    pub score: i64,
+    /// The score normalized between 0.0 and 1.0.
+    pub weight: f64,
+    /// Char indices of the text that were matched by the pattern.
+    pub positions: Vec<usize>,
+}
+
+/// Finds the best scoring occurrence of `pattern` as a subsequence of `text`.
+///
+/// The pattern is expected to be folded with [`normalize::fold`]. Matched characters
+/// are rewarded for appearing at word boundaries and for being consecutive, and
+/// penalized for gaps.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    fuzzy_match_with(pattern, text, true)
+}
+
+/// Finds the best scoring occurrence of `pattern` as a subsequence of `text`, which
+/// keeps its diacritics unless it is to be matched accent-insensitively.
+///
+/// The pattern is expected to be folded with [`normalize::fold_with`], with the same
+/// accent sensitivity.
+pub fn fuzzy_match_with(pattern: &str, text: &str, accent_insensitive: bool) -> Option<Match> {
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            weight: 0.0,
+            positions: Vec::new(),
+        });
+    }
+
+    // Folding may expand a character into several, so each folded character
+    // remembers the position of the character it was folded from.
+    let mut folded = Vec::with_capacity(text.len());
+    let mut bonuses = Vec::with_capacity(text.len());
+    let mut prev_class = CharClass::NonWord;
+
+    for (idx, character) in text.chars().enumerate() {
+        let class = CharClass::of(character);
+        let bonus = bonus(prev_class, class);
+
+        let mut push = |folded_char| {
+            folded.push((folded_char, idx));
+            bonuses.push(bonus);
+        };
+
+        if accent_insensitive {
+            normalize::fold_char(character).for_each(&mut push);
+        } else {
+            normalize::casefold_char(character).for_each(&mut push);
+        }
+
+        prev_class = class;
+    }
+
+    if pattern.len() > folded.len() {
+        return None;
+    }
+
+    // For each pattern character and text position, the best score of a match
+    // that ends there, the bonus of the run it belongs to, and where the previous
+    // pattern character was matched.
+    let mut cells: Vec<Vec<Option<Cell>>> = vec![vec![None; folded.len()]; pattern.len()];
+
+    for (pidx, &expected) in pattern.iter().enumerate() {
+        // The best match of the previous pattern character which leaves a gap before
+        // the current text position, carried along the row so that each cell need
+        // not scan every earlier position.
+        let mut gapped: Option<Cell> = None;
+
+        for tidx in pidx..folded.len() {
+            if pidx > 0 && tidx >= 2 {
+                // Every gap grows by one as the text position advances
+                gapped = gapped.map(|cell| Cell {
+                    score: cell.score - PENALTY_GAP_EXTENSION,
+                    ..cell
+                });
+
+                if let Some(cell) = cells[pidx - 1][tidx - 2] {
+                    let score = cell.score - PENALTY_GAP_START;
+                    if gapped.is_none_or(|gapped| score > gapped.score) {
+                        gapped = Some(Cell {
+                            score,
+                            run_bonus: 0,
+                            prev: tidx - 2,
+                        });
+                    }
+                }
+            }
+
+            if folded[tidx].0 != expected {
+                continue;
+            }
+
+            let bonus = bonuses[tidx];
+
+            if pidx == 0 {
+                cells[pidx][tidx] = Some(Cell {
+                    score: SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER,
+                    run_bonus: bonus,
+                    prev: 0,
+                });
+
+                continue;
+            }
+
+            let mut best = gapped.map(|cell| Cell {
+                score: cell.score + SCORE_MATCH + bonus,
+                run_bonus: bonus,
+                prev: cell.prev,
+            });
+
+            if let Some(cell) = cells[pidx - 1][tidx - 1] {
+                let run_bonus = cell.run_bonus.max(bonus).max(BONUS_CONSECUTIVE);
+                let consecutive = Cell {
+                    score: cell.score + SCORE_MATCH + run_bonus,
+                    run_bonus,
+                    prev: tidx - 1,
+                };
+
+                if best.is_none_or(|best| consecutive.score > best.score) {
+                    best = Some(consecutive);
+                }
+            }
+
+            cells[pidx][tidx] = best;
+        }
+    }
+
+    let last = pattern.len() - 1;
+
+    let (mut tidx, score) = cells[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(tidx, cell)| cell.map(|cell| (tidx, cell.score)))
+        .fold(
+            None,
+            |best: Option<(usize, i64)>, (tidx, score)| match best {
+                Some((_, best_score)) if best_score >= score => best,
+                _ => Some((tidx, score)),
+            },
+        )?;
+
+    let mut positions = Vec::with_capacity(pattern.len());
+
+    for pidx in (0..pattern.len()).rev() {
+        positions.push(folded[tidx].1);
+        if let Some(cell) = cells[pidx][tidx] {
+            tidx = cell.prev;
+        }
+    }
+
+    positions.reverse();
+    positions.dedup();
+
+    let max_score = pattern.len() as i64 * (SCORE_MATCH + BONUS_BOUNDARY)
+        + BONUS_BOUNDARY * (BONUS_FIRST_CHAR_MULTIPLIER - 1);
+
+    Some(Match {
+        score,
+        weight: (score as f64 / max_score as f64).clamp(0.0, 1.0),
+        positions,
+    })
+}
+
+#[derive(Copy, Clone)]
+struct Cell {
+    score: i64,
+    run_bonus: i64,
+    prev: usize,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum CharClass {
+    Lower,
+    Upper,
+    Number,
+    NonWord,
+}
+
+impl CharClass {
+    fn of(character: char) -> Self {
+        if character.is_lowercase() {
+            CharClass::Lower
+        } else if character.is_uppercase() {
+            CharClass::Upper
+        } else if character.is_numeric() {
+            CharClass::Number
+        } else if character.is_alphabetic() {
+            CharClass::Lower
+        } else {
+            CharClass::NonWord
+        }
+    }
+}
+
+fn bonus(prev: CharClass, class: CharClass) -> i64 {
+    match (prev, class) {
+        (CharClass::NonWord, CharClass::NonWord) => BONUS_NON_WORD,
+        (CharClass::NonWord, _) => BONUS_BOUNDARY,
+        (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL,
+        (CharClass::Lower, CharClass::Number) | (CharClass::Upper, CharClass::Number) => {
+            BONUS_CAMEL
+        }
+        (_, CharClass::NonWord) => BONUS_NON_WORD,
+        _ => 0,
+    }
+}
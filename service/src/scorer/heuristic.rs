@@ -0,0 +1,60 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use super::Scorer;
+use pop_launcher::{normalize, PluginSearchResult};
+
+/// Favors names and commands that begin with the query, and otherwise falls back
+/// to the Jaro-Winkler similarity of the name, description, command, and keywords.
+pub struct HeuristicScorer {
+    /// Strips diacritics along with case, such that `ecran` matches `Écran`.
+    pub accent_insensitive: bool,
+}
+
+impl Default for HeuristicScorer {
+    fn default() -> Self {
+        Self {
+            accent_insensitive: true,
+        }
+    }
+}
+
+impl Scorer for HeuristicScorer {
+    fn score(&self, query: &str, meta: &PluginSearchResult) -> f64 {
+        let fold = |text: &str| normalize::fold_with(text, self.accent_insensitive);
+
+        let mut weight: f64 = 0.0;
+
+        let name = fold(&meta.name);
+        let description = fold(&meta.description);
+        let exec = meta.exec.as_deref().map(fold).unwrap_or_default();
+
+        for name in name.split_ascii_whitespace().flat_map(|x| x.split('_')) {
+            if name.starts_with(query) {
+                return 1.0;
+            }
+        }
+
+        if exec.contains(query) {
+            if exec.starts_with(query) {
+                return 1.0;
+            } else {
+                weight = strsim::jaro_winkler(query, &exec) - 0.1;
+            }
+        }
+
+        weight
+            .max(strsim::jaro_winkler(&name, query))
+            .max(strsim::jaro_winkler(&description, query) - 0.1)
+            .max(match meta.keywords.as_ref() {
+                Some(keywords) => keywords
+                    .iter()
+                    .flat_map(|word| word.split_ascii_whitespace())
+                    .fold(0.0, |acc, keyword| {
+                        let keyword = fold(keyword);
+                        acc.max(strsim::jaro_winkler(query, &keyword) - 0.1)
+                    }),
+                None => 0.0,
+            })
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+mod fuzzy;
+mod heuristic;
+
+pub use self::fuzzy::{fuzzy_match, fuzzy_match_with, FuzzyScorer, Match};
+pub use self::heuristic::HeuristicScorer;
+
+use pop_launcher::PluginSearchResult;
+
+/// Calculates how similar a search result is to a query.
+pub trait Scorer: Send + Sync {
+    /// Weight is calculated between 0.0 and 1.0, with higher values being most similar.
+    ///
+    /// The query is expected to be folded with [`pop_launcher::normalize::fold_with`],
+    /// stripping diacritics if the scorer is accent-insensitive.
+    fn score(&self, query: &str, meta: &PluginSearchResult) -> f64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pop_launcher::normalize;
+    use std::cmp::Ordering;
+
+    fn result(name: &str, exec: Option<&str>, keywords: &[&str]) -> PluginSearchResult {
+        PluginSearchResult {
+            name: name.to_owned(),
+            exec: exec.map(String::from),
+            keywords: if keywords.is_empty() {
+                None
+            } else {
+                Some(keywords.iter().map(|&k| String::from(k)).collect())
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Sorts names by weight in the same manner as the service.
+    fn rank(scorer: &dyn Scorer, query: &str, results: &[PluginSearchResult]) -> Vec<String> {
+        let mut weighted: Vec<(f64, &PluginSearchResult)> = results
+            .iter()
+            .map(|meta| (scorer.score(query, meta), meta))
+            .collect();
+
+        weighted.sort_by(|a, b| match b.0.partial_cmp(&a.0) {
+            Some(Ordering::Equal) | None => a.1.name.len().cmp(&b.1.name.len()),
+            Some(ordering) => ordering,
+        });
+
+        weighted
+            .into_iter()
+            .map(|(_, meta)| meta.name.clone())
+            .collect()
+    }
+
+    fn apps() -> Vec<PluginSearchResult> {
+        vec![
+            result("Files", Some("nautilus"), &["folder", "manager"]),
+            result("Firefox Web Browser", Some("firefox"), &["internet"]),
+            result("Fire Alarm", None, &[]),
+            result("GNOME Terminal", Some("gnome-terminal"), &["shell"]),
+            result("Visual Studio Code", Some("code"), &["editor"]),
+            result("VirtualBox", Some("virtualbox"), &[]),
+            result("GNU Image Manipulation Program", Some("gimp"), &[]),
+            result("Écran de veille", Some("xscreensaver"), &[]),
+            result("Терминал", None, &[]),
+        ]
+    }
+
+    #[test]
+    fn heuristic_ranking() {
+        const CASES: &[(&str, &[&str])] = &[
+            ("fire", &["Fire Alarm", "Firefox Web Browser"]),
+            ("term", &["GNOME Terminal"]),
+            ("code", &["Visual Studio Code"]),
+            ("gimp", &["GNU Image Manipulation Program"]),
+            ("nautilus", &["Files"]),
+            ("ecran", &["Écran de veille"]),
+            ("терм", &["Терминал"]),
+        ];
+
+        let apps = apps();
+
+        for (query, expected) in CASES {
+            let ranked = rank(&HeuristicScorer::default(), query, &apps);
+            assert_eq!(
+                expected,
+                &&ranked[..expected.len()],
+                "query {:?} ranked {:?}",
+                query,
+                ranked
+            );
+        }
+    }
+
+    #[test]
+    fn fuzzy_ranking() {
+        const CASES: &[(&str, &[&str])] = &[
+            ("fire", &["Fire Alarm", "Firefox Web Browser"]),
+            ("vsc", &["Visual Studio Code"]),
+            ("gterm", &["GNOME Terminal"]),
+            ("gimp", &["GNU Image Manipulation Program"]),
+            ("fwb", &["Firefox Web Browser"]),
+            ("edv", &["Écran de veille"]),
+            ("трм", &["Терминал"]),
+        ];
+
+        let apps = apps();
+
+        for (query, expected) in CASES {
+            let ranked = rank(&FuzzyScorer::default(), query, &apps);
+            assert_eq!(
+                expected,
+                &&ranked[..expected.len()],
+                "query {:?} ranked {:?}",
+                query,
+                ranked
+            );
+        }
+    }
+
+    #[test]
+    fn fuzzy_positions() {
+        const CASES: &[(&str, &str, Option<&[usize]>)] = &[
+            ("fox", "Firefox", Some(&[0, 5, 6])),
+            ("refo", "Firefox", Some(&[2, 3, 4, 5])),
+            ("vsc", "Visual Studio Code", Some(&[0, 7, 14])),
+            ("term", "gnome-terminal", Some(&[6, 7, 8, 9])),
+            ("xyz", "Firefox", None),
+            ("ffox", "Firefox", Some(&[0, 4, 5, 6])),
+            ("strasse", "Straße", Some(&[0, 1, 2, 3, 4, 5])),
+        ];
+
+        for (pattern, text, expected) in CASES {
+            let positions = fuzzy_match(pattern, text).map(|m| m.positions);
+            assert_eq!(
+                expected.map(<[usize]>::to_vec),
+                positions,
+                "{:?} in {:?}",
+                pattern,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn accent_sensitive_ranking() {
+        let apps = vec![
+            result("Écran de veille", None, &[]),
+            result("Ecrans", None, &[]),
+        ];
+
+        let heuristic = HeuristicScorer {
+            accent_insensitive: false,
+        };
+
+        let fuzzy = FuzzyScorer {
+            accent_insensitive: false,
+        };
+
+        for scorer in [&heuristic as &dyn Scorer, &fuzzy] {
+            let query = &normalize::fold_with("écran", false);
+            assert_eq!("Écran de veille", rank(scorer, query, &apps)[0]);
+
+            let query = &normalize::fold_with("ecran", false);
+            assert_eq!("Ecrans", rank(scorer, query, &apps)[0]);
+        }
+
+        assert!(fuzzy_match_with("ecran", "Écran", false).is_none());
+        assert!(fuzzy_match_with("ecran", "Écran", true).is_some());
+    }
+
+    #[test]
+    fn fuzzy_long_text() {
+        let text = format!("{}pop-launcher", "/usr/share/doc/".repeat(2000));
+        let start = text.chars().count() - "pop-launcher".len();
+
+        let positions = fuzzy_match("launcher", &text).unwrap().positions;
+        assert_eq!((start + 4..start + 12).collect::<Vec<_>>(), positions);
+    }
+
+    #[test]
+    fn fuzzy_prefers_boundaries_and_runs() {
+        let boundary = fuzzy_match("sc", "Studio Code").unwrap();
+        let inner = fuzzy_match("sc", "Describe").unwrap();
+        assert!(boundary.score > inner.score);
+
+        let consecutive = fuzzy_match("cod", "Code").unwrap();
+        let scattered = fuzzy_match("cod", "Color Disc").unwrap();
+        assert!(consecutive.score > scattered.score);
+        assert_eq!(1.0, consecutive.weight);
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::PluginKey;
+use flume::Sender;
+use pop_launcher::*;
+use std::collections::HashMap;
+use tokio::time::Instant;
+
+/// Identifies a frontend connected to the service.
+pub type SessionKey = usize;
+
+/// Search state of a frontend connected to the service.
+pub struct Session {
+    pub active_search: Vec<(PluginKey, PluginSearchResult)>,
+    pub associated_list: HashMap<Indice, Indice>,
+    /// Plugins which are searching, and the deadline for them to finish
+    pub awaiting_results: HashMap<PluginKey, Instant>,
+    /// What the frontend understands, as advertised in its `Hello`
+    pub capabilities: Capabilities,
+    /// Expansions of the aliases suggested with the latest results, in the order shown
+    pub hints: Vec<String>,
+    pub isolated: Option<PluginKey>,
+    pub last_query: Query,
+    pub no_sort: bool,
+    /// Position among the queries being recalled, if the frontend is recalling them
+    pub recalling: Option<Recalling>,
+    /// Where responses to this frontend are sent, or the service's own output if `None`
+    pub output: Option<Sender<Response>>,
+    /// ID of the latest search sent to plugins, which they echo in their results
+    pub search_id: QueryId,
+    pub search_scheduled: bool,
+    pub update_deadline: Option<Instant>,
+}
+
+/// Progress of a frontend through the queries that it is recalling.
+pub struct Recalling {
+    /// Text which was typed before recalling began, which is returned to afterwards
+    pub typed: String,
+    /// Number of queries stepped back through, with zero being the typed text
+    pub position: usize,
+    /// The query which was last filled in
+    pub filled: String,
+}
+
+impl Session {
+    pub fn new(output: Option<Sender<Response>>) -> Self {
+        Self {
+            active_search: Vec::new(),
+            associated_list: HashMap::new(),
+            awaiting_results: HashMap::new(),
+            capabilities: Capabilities::default(),
+            hints: Vec::new(),
+            isolated: None,
+            last_query: Query::default(),
+            no_sort: false,
+            recalling: None,
+            output,
+            search_id: 0,
+            search_scheduled: false,
+            update_deadline: None,
+        }
+    }
+
+    /// The earliest instant at which the service must act on this session.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.awaiting_results
+            .values()
+            .chain(self.update_deadline.iter())
+            .min()
+            .copied()
+    }
+}
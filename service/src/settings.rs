@@ -0,0 +1,144 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::alias::Aliases;
+use crate::scorer::{FuzzyScorer, HeuristicScorer, Scorer};
+use crate::{PluginConfig, PluginOverlay};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Configuration of the launcher service, read from `launcher.ron`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Ranks results while ignoring diacritics, such that `ecran` matches `Écran`.
+    pub accent_insensitive: bool,
+
+    /// Words which expand into the text given when they begin a query.
+    pub aliases: Aliases,
+
+    /// Displays results referring to windows ahead of all other results.
+    pub hoist_windows: bool,
+
+    /// Seconds without requests after which a plugin's process is stopped, unless
+    /// the plugin defines its own. Plugins are kept running if this is unset.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub idle_timeout: Option<u64>,
+
+    /// Runs the plugins bundled with the launcher within the service, rather than
+    /// in processes of their own, unless a plugin's override says otherwise. A panic
+    /// in such a plugin aborts the whole service.
+    pub in_process: bool,
+
+    /// Maximum number of results that are displayed, unless a query is isolated
+    /// to a plugin which defines its own limit.
+    pub max_results: usize,
+
+    /// Overrides for plugins, by plugin name.
+    pub plugins: HashMap<String, PluginOverlay>,
+
+    pub scorer: ScorerKind,
+
+    /// Milliseconds to wait for more plugins to finish before streaming results.
+    pub stream_debounce: u64,
+
+    /// Streams results to the frontend as plugins finish, rather than waiting for
+    /// every plugin to finish.
+    pub stream_results: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            accent_insensitive: true,
+            aliases: Aliases::new(),
+            hoist_windows: true,
+            idle_timeout: None,
+            in_process: false,
+            max_results: 8,
+            plugins: HashMap::new(),
+            scorer: ScorerKind::default(),
+            stream_debounce: 50,
+            stream_results: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Applies the defaults and overrides defined for a plugin to its config.
+    pub fn apply(&self, config: &mut PluginConfig) {
+        if let Some(overlay) = self.plugins.get(config.name.as_ref()) {
+            config.overlay(overlay);
+        }
+
+        if config.idle_timeout.is_none() {
+            config.idle_timeout = self.idle_timeout;
+        }
+    }
+
+    /// Whether the overrides applied to a plugin differ from those of the previous settings.
+    pub fn overrides_changed(&self, previous: &Settings, name: &str) -> bool {
+        self.idle_timeout != previous.idle_timeout
+            || self.plugins.get(name) != previous.plugins.get(name)
+    }
+
+    /// Whether the plugin should run within the service, if it is able to.
+    pub fn in_process(&self, name: &str) -> bool {
+        self.plugins
+            .get(name)
+            .and_then(|overlay| overlay.in_process)
+            .unwrap_or(self.in_process)
+    }
+
+    /// Whether the plugin has not been disabled by the user.
+    pub fn enabled(&self, name: &str) -> bool {
+        self.plugins
+            .get(name)
+            .and_then(|overlay| overlay.enabled)
+            .unwrap_or(true)
+    }
+}
+
+/// Selects the algorithm used to rank search results.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub enum ScorerKind {
+    /// Prefix checks combined with Jaro-Winkler similarity.
+    #[default]
+    Heuristic,
+    /// fzf-style subsequence matching.
+    Fuzzy,
+}
+
+impl ScorerKind {
+    pub fn scorer(self, accent_insensitive: bool) -> Box<dyn Scorer> {
+        match self {
+            Self::Heuristic => Box::new(HeuristicScorer { accent_insensitive }),
+            Self::Fuzzy => Box::new(FuzzyScorer { accent_insensitive }),
+        }
+    }
+}
+
+/// Loads the launcher configuration with the highest priority.
+pub fn load() -> Settings {
+    for path in pop_launcher::config::find_file("launcher.ron") {
+        let string = match std::fs::read_to_string(&path) {
+            Ok(string) => string,
+            Err(why) => {
+                tracing::error!("failed to read {}: {}", path.display(), why);
+                continue;
+            }
+        };
+
+        match ron::from_str::<Settings>(&string) {
+            Ok(settings) => return settings,
+            Err(why) => {
+                tracing::error!("malformed config at {}: {}", path.display(), why);
+            }
+        }
+    }
+
+    Settings::default()
+}
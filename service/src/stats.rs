@@ -0,0 +1,79 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Prints the latency and health of each plugin, as measured by a running daemon.
+
+use crate::DaemonClient;
+use futures::StreamExt;
+use pop_launcher::{Latency, PluginStats, Request, Response};
+
+pub async fn main() {
+    let (mut client, responses) = match DaemonClient::connect().await {
+        Ok(client) => client,
+        Err(why) => {
+            eprintln!(
+                "failed to connect to the launcher daemon at {}: {}",
+                crate::daemon::socket_path().display(),
+                why
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(why) = client.send(Request::Stats).await {
+        eprintln!("failed to request stats: {}", why);
+        std::process::exit(1);
+    }
+
+    futures::pin_mut!(responses);
+
+    while let Some(response) = responses.next().await {
+        if let Response::Stats(stats) = response {
+            print(&stats);
+            break;
+        }
+    }
+
+    client.exit().await;
+}
+
+fn print(stats: &[PluginStats]) {
+    let width = stats
+        .iter()
+        .map(|stats| stats.plugin.chars().count())
+        .chain(Some("PLUGIN".len()))
+        .max()
+        .unwrap_or_default();
+
+    println!(
+        "{:width$}  {:>8}  {:>20}  {:>20}  {:>8}  {:>7}",
+        "PLUGIN",
+        "SEARCHES",
+        "FIRST RESULT",
+        "FINISHED",
+        "TIMEOUTS",
+        "CRASHES",
+        width = width
+    );
+
+    for stats in stats {
+        println!(
+            "{:width$}  {:>8}  {:>20}  {:>20}  {:>8}  {:>7}",
+            stats.plugin,
+            stats.searches,
+            latency(&stats.first_result),
+            latency(&stats.finished),
+            stats.timeouts,
+            stats.crashes,
+            width = width
+        );
+    }
+}
+
+/// Mean and maximum latency, or a dash if nothing was measured.
+fn latency(latency: &Latency) -> String {
+    match latency.mean() {
+        Some(mean) => format!("{} ms (max {})", mean, latency.max),
+        None => String::from("-"),
+    }
+}
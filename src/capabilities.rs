@@ -0,0 +1,79 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::Response;
+use serde::{Deserialize, Serialize};
+
+/// Version of the protocol defined by this crate.
+///
+/// Peers which never introduce themselves with a `Hello` are assumed to speak version 0,
+/// which predates every optional capability.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Parts of the protocol understood by a peer, exchanged in `Hello` messages.
+///
+/// Capabilities unknown to the receiver are ignored, and capabilities missing
+/// from a `Hello` are assumed to be unsupported.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Capabilities {
+    /// Version of the protocol spoken by the peer.
+    pub version: u32,
+
+    /// Understands `Response::Error`.
+    pub error: bool,
+
+    /// Understands `Response::PartialUpdate`.
+    pub partial_update: bool,
+
+    /// Understands `Response::PluginFailed`.
+    pub plugin_failed: bool,
+
+    /// Understands queries given as objects, with an ID and trigger, and search
+    /// results tagged with an ID.
+    pub query_id: bool,
+}
+
+impl Capabilities {
+    /// Everything understood by this version of the protocol.
+    pub fn current() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            error: true,
+            partial_update: true,
+            plugin_failed: true,
+            query_id: true,
+        }
+    }
+
+    /// Whether a peer with these capabilities is able to deserialize the response.
+    pub fn understands(&self, response: &Response) -> bool {
+        match response {
+            Response::Error { .. } => self.error,
+            Response::PartialUpdate { .. } => self.partial_update,
+            Response::PluginFailed { .. } => self.plugin_failed,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_capabilities_are_ignored() {
+        let capabilities: Capabilities =
+            serde_json::from_str(r#"{"version":7,"query_id":true,"from_the_future":true}"#)
+                .unwrap();
+
+        assert_eq!(
+            Capabilities {
+                version: 7,
+                query_id: true,
+                ..Capabilities::default()
+            },
+            capabilities
+        );
+    }
+}
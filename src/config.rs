@@ -20,3 +20,10 @@ pub fn find(name: &'_ str) -> impl Iterator<Item = PathBuf> + '_ {
             })
         })
 }
+
+/// Locates a launcher configuration file by name, in order of priority.
+pub fn find_file(name: &'_ str) -> impl Iterator<Item = PathBuf> + '_ {
+    crate::paths()
+        .map(move |path| path.join(name))
+        .filter(|path| path.exists())
+}
@@ -1,10 +1,17 @@
 // Copyright 2021 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
+mod capabilities;
 mod codec;
 pub mod config;
+pub mod normalize;
+mod query;
+mod stats;
 
+pub use self::capabilities::*;
 pub use self::codec::*;
+pub use self::query::*;
+pub use self::stats::*;
 
 use const_format::concatcp;
 use serde::{Deserialize, Serialize};
@@ -22,20 +29,28 @@ pub const SYSTEM_PLUGINS: &str = concatcp!(SYSTEM, "/plugins");
 pub const DISTRIBUTION: &str = "/usr/lib/pop-launcher";
 pub const DISTRIBUTION_PLUGINS: &str = concatcp!(DISTRIBUTION, "/plugins");
 
+pub const PATHS: &[&str] = &[LOCAL, SYSTEM, DISTRIBUTION];
+
 pub const PLUGIN_PATHS: &[&str] = &[LOCAL_PLUGINS, SYSTEM_PLUGINS, DISTRIBUTION_PLUGINS];
 
+pub fn paths() -> impl Iterator<Item = Cow<'static, Path>> {
+    PATHS.iter().map(|path| expand_home(path))
+}
+
 pub fn plugin_paths() -> impl Iterator<Item = Cow<'static, Path>> {
-    PLUGIN_PATHS.iter().map(|path| {
-        #[allow(deprecated)]
-        if let Some(path) = path.strip_prefix("~/") {
-            let path = dirs::home_dir()
-                .expect("user does not have home dir")
-                .join(path);
-            Cow::Owned(path)
-        } else {
-            Cow::Borrowed(Path::new(path))
-        }
-    })
+    PLUGIN_PATHS.iter().map(|path| expand_home(path))
+}
+
+fn expand_home(path: &'static str) -> Cow<'static, Path> {
+    #[allow(deprecated)]
+    if let Some(path) = path.strip_prefix("~/") {
+        let path = dirs::home_dir()
+            .expect("user does not have home dir")
+            .join(path);
+        Cow::Owned(path)
+    } else {
+        Cow::Borrowed(Path::new(path))
+    }
 }
 
 /// u32 value defining the generation of an indice.
@@ -50,13 +65,25 @@ pub struct ContextOption {
     pub name: String,
 }
 
+/// Category of an error reported by a plugin.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A program or library which the plugin depends on is not installed.
+    MissingDependency,
+    /// A service which the plugin depends on could not be reached.
+    Unavailable,
+    /// Any other failure, including kinds unknown to this version of the protocol.
+    #[serde(other)]
+    Failed,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum GpuPreference {
     Default,
     NonDefault,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
 pub enum IconSource {
     // Locate by name or path.
     Name(Cow<'static, str>),
@@ -85,10 +112,15 @@ pub enum PluginResponse {
         path: PathBuf,
         gpu_preference: GpuPreference,
     },
+    /// Reports a failure to the frontend, in place of results.
+    Error { kind: ErrorKind, message: String },
     /// Update the text in the launcher.
     Fill(String),
     /// Indicoates that a plugin is finished with its queries.
     Finished,
+    /// Introduces the plugin, which the service answers with a `Request::Hello`
+    /// before sending its next request.
+    Hello(Capabilities),
 }
 
 /// Search information from a plugin to be sorted and filtered by the launcher service.
@@ -108,6 +140,37 @@ pub struct PluginSearchResult {
     pub exec: Option<String>,
     /// Designates that this search item refers to a window.
     pub window: Option<(Generation, Indice)>,
+    /// ID of the query that this result was found for, if the query had one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_id: Option<QueryId>,
+    /// Identifies what the result refers to, such as the URI of a file or the ID of
+    /// an application. Results from several plugins with the same key are collapsed
+    /// into the result of the plugin with the highest priority.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedupe_key: Option<String>,
+}
+
+impl PluginSearchResult {
+    /// A result with an ID and a name, whose other fields are left to their defaults.
+    ///
+    /// Fields are added to results as the protocol grows, so plugins which do not
+    /// construct them with `..Default::default()` should construct them with this.
+    pub fn new(id: Indice, name: impl Into<String>) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Which way to step through the queries recalled by a `Request::History`.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub enum Direction {
+    /// Towards older queries, as with the up arrow.
+    Previous,
+    /// Towards newer queries, and then back to the text that was typed.
+    Next,
 }
 
 // Sent to the input pipe of the launcher service, and disseminated to its plugins.
@@ -123,12 +186,24 @@ pub enum Request {
     Context(Indice),
     /// Request to end the service.
     Exit,
+    /// Introduces a frontend to the service, or the service to a plugin which
+    /// introduced itself. The service answers a frontend with a `Response::Hello`.
+    Hello(Capabilities),
+    /// Recalls a query which led to an activation, which the service answers with
+    /// a `Response::Fill`.
+    History { direction: Direction },
     /// Requests to cancel any active searches.
     Interrupt,
+    /// Requests a further slice of the results of the latest search, beyond those
+    /// of its update, which the service answers with a `Response::Page`.
+    Page { offset: usize, limit: usize },
     /// Request to close the selected item.
     Quit(Indice),
     /// Perform a search in our database.
-    Search(String),
+    Search(Query),
+    /// Requests the latency and health of each plugin, which the service answers
+    /// with a `Response::Stats`.
+    Stats,
 }
 
 /// Sent from the launcher service to a frontend.
@@ -147,9 +222,41 @@ pub enum Response {
         gpu_preference: GpuPreference,
     },
     // The frontend should clear its search results and display a new list.
-    Update(Vec<SearchResult>),
+    Update(SearchResults),
     // An item was selected that resulted in a need to autofill the launcher.
     Fill(String),
+    // Like `Update`, but sent as plugins finish searching when streaming is enabled.
+    // More updates for the same query will follow while `pending` is true.
+    PartialUpdate {
+        results: Vec<SearchResult>,
+        pending: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<QueryId>,
+    },
+    // A plugin was disabled after crashing or failing to respond too often.
+    PluginFailed {
+        plugin: String,
+        reason: String,
+    },
+    // Answers a frontend's `Request::Hello` with the capabilities of the service.
+    Hello(Capabilities),
+    // A plugin failed to perform a request, and the frontend may display a notice.
+    Error {
+        plugin: String,
+        kind: ErrorKind,
+        message: String,
+    },
+    // Answers a frontend's `Request::Stats` with the stats of each plugin.
+    Stats(Vec<PluginStats>),
+    // Answers a frontend's `Request::Page` with a slice of the results of the latest
+    // search, starting at `offset`, out of the `total` which may be displayed.
+    Page {
+        results: Vec<SearchResult>,
+        offset: usize,
+        total: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<QueryId>,
+    },
 }
 
 /// Serialized response to launcher frontend about a search result.
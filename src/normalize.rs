@@ -0,0 +1,99 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Text normalization for caseless and accent-insensitive search matching.
+
+use caseless::Caseless;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Applies full Unicode case folding, such that `Straße` and `STRASSE` compare equal.
+pub fn casefold(input: &str) -> String {
+    input.nfd().default_case_fold().nfc().collect()
+}
+
+/// Removes diacritics, such that `Écran` becomes `Ecran`.
+pub fn strip_accents(input: &str) -> String {
+    input
+        .nfd()
+        .filter(|&character| !is_combining_mark(character))
+        .nfc()
+        .collect()
+}
+
+/// Case folds and strips diacritics from text, so that it may be compared to a query.
+pub fn fold(input: &str) -> String {
+    input.chars().flat_map(fold_char).collect()
+}
+
+/// Case folds and strips diacritics from a single character, which may expand
+/// into multiple characters.
+pub fn fold_char(character: char) -> impl Iterator<Item = char> {
+    std::iter::once(character)
+        .default_case_fold()
+        .nfd()
+        .filter(|&character| !is_combining_mark(character))
+}
+
+/// Case folds a single character, which may expand into multiple characters, and
+/// keeps its diacritics composed with it.
+pub fn casefold_char(character: char) -> impl Iterator<Item = char> {
+    std::iter::once(character).default_case_fold().nfc()
+}
+
+/// Folds text with [`fold`] if accents are to be ignored, or with [`casefold`] if not.
+pub fn fold_with(input: &str, accent_insensitive: bool) -> String {
+    if accent_insensitive {
+        fold(input)
+    } else {
+        casefold(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn german() {
+        assert_eq!("ärzte", casefold("ÄRZTE"));
+        assert_eq!("arzte", fold("Ärzte"));
+        assert_eq!("strasse", fold("Straße"));
+        assert_eq!(fold("STRASSE"), fold("straße"));
+    }
+
+    #[test]
+    fn french() {
+        assert_eq!("écran", casefold("Écran"));
+        assert_eq!("Ecran", strip_accents("Écran"));
+        assert_eq!("ecran", fold("Écran"));
+        assert_eq!("francais", fold("Français"));
+        assert!(fold("Paramètres du Système").contains(&fold("systeme")));
+    }
+
+    #[test]
+    fn accent_sensitive() {
+        assert_eq!(fold_with("Écran", false), fold_with("éCRAN", false));
+        assert_ne!(fold_with("Écran", false), fold_with("ecran", false));
+        assert_eq!("ecran", fold_with("Écran", true));
+        assert_eq!("é", casefold_char('É').collect::<String>());
+        assert_eq!("ss", casefold_char('ß').collect::<String>());
+    }
+
+    #[test]
+    fn turkish() {
+        assert_eq!("istanbul", fold("İstanbul"));
+        assert_eq!("izmir", fold("İZMİR"));
+        assert_eq!("igdir", fold("IĞDIR"));
+        // The dotless i has no decomposition, and folds only to itself.
+        assert_eq!("ı", fold("ı"));
+    }
+
+    #[test]
+    fn cyrillic() {
+        assert_eq!("терминал", casefold("Терминал"));
+        assert_eq!(fold("настройки"), fold("НАСТРОЙКИ"));
+        assert!(fold("Файловый менеджер").contains(&fold("ФАЙЛ")));
+        // Diacritics are stripped from Cyrillic letters as well.
+        assert_eq!("еж", fold("Ёж"));
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::SearchResult;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+/// Identifies a search query, so that responses to superseded queries may be discarded.
+pub type QueryId = u64;
+
+/// Text to search for, optionally tagged with an ID that responses to it will echo.
+///
+/// Untagged queries are serialized as a plain string, as they were before IDs existed.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(from = "QueryRepr", into = "QueryRepr")]
+pub struct Query {
+    pub text: String,
+    pub id: Option<QueryId>,
+    /// The prefix which preceded the text, when a plugin's pattern captured the
+    /// text of the query apart from the prefix which triggered the plugin.
+    pub trigger: Option<String>,
+    /// Whether the query is to be matched with regard to its accents, as the service
+    /// has been configured to be accent sensitive.
+    pub keep_accents: bool,
+}
+
+impl Deref for Query {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<String> for Query {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            id: None,
+            trigger: None,
+            keep_accents: false,
+        }
+    }
+}
+
+impl From<&str> for Query {
+    fn from(text: &str) -> Self {
+        Self::from(text.to_owned())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum QueryRepr {
+    Text(String),
+    Tagged {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<QueryId>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        trigger: Option<String>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        keep_accents: bool,
+    },
+}
+
+impl From<QueryRepr> for Query {
+    fn from(repr: QueryRepr) -> Self {
+        match repr {
+            QueryRepr::Text(text) => Self::from(text),
+            QueryRepr::Tagged {
+                text,
+                id,
+                trigger,
+                keep_accents,
+            } => Self {
+                text,
+                id,
+                trigger,
+                keep_accents,
+            },
+        }
+    }
+}
+
+impl From<Query> for QueryRepr {
+    fn from(query: Query) -> Self {
+        if query.id.is_none() && query.trigger.is_none() && !query.keep_accents {
+            return Self::Text(query.text);
+        }
+
+        Self::Tagged {
+            text: query.text,
+            id: query.id,
+            trigger: query.trigger,
+            keep_accents: query.keep_accents,
+        }
+    }
+}
+
+/// Sorted search results, tagged with the ID of the query they were found for.
+///
+/// Untagged results are serialized as a plain list, as they were before IDs existed.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(from = "SearchResultsRepr", into = "SearchResultsRepr")]
+pub struct SearchResults {
+    pub results: Vec<SearchResult>,
+    pub id: Option<QueryId>,
+}
+
+impl Deref for SearchResults {
+    type Target = Vec<SearchResult>;
+
+    fn deref(&self) -> &Vec<SearchResult> {
+        &self.results
+    }
+}
+
+impl From<Vec<SearchResult>> for SearchResults {
+    fn from(results: Vec<SearchResult>) -> Self {
+        Self { results, id: None }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum SearchResultsRepr {
+    List(Vec<SearchResult>),
+    Tagged {
+        results: Vec<SearchResult>,
+        id: Option<QueryId>,
+    },
+}
+
+impl From<SearchResultsRepr> for SearchResults {
+    fn from(repr: SearchResultsRepr) -> Self {
+        match repr {
+            SearchResultsRepr::List(results) => Self { results, id: None },
+            SearchResultsRepr::Tagged { results, id } => Self { results, id },
+        }
+    }
+}
+
+impl From<SearchResults> for SearchResultsRepr {
+    fn from(results: SearchResults) -> Self {
+        match results.id {
+            Some(id) => Self::Tagged {
+                results: results.results,
+                id: Some(id),
+            },
+            None => Self::List(results.results),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn untagged_query_is_a_string() {
+        let request: Request = serde_json::from_str(r#"{"Search":"firefox"}"#).unwrap();
+        assert!(matches!(&request, Request::Search(query) if query.id.is_none()));
+        assert_eq!(
+            r#"{"Search":"firefox"}"#,
+            serde_json::to_string(&request).unwrap()
+        );
+    }
+
+    #[test]
+    fn tagged_query_round_trips() {
+        let json = r#"{"Search":{"text":"firefox","id":7}}"#;
+        let request: Request = serde_json::from_str(json).unwrap();
+
+        match &request {
+            Request::Search(query) => {
+                assert_eq!("firefox", &**query);
+                assert_eq!(Some(7), query.id);
+            }
+            _ => panic!("expected search request"),
+        }
+
+        assert_eq!(json, serde_json::to_string(&request).unwrap());
+    }
+
+    #[test]
+    fn query_with_trigger_is_tagged() {
+        let query = Query {
+            text: String::from("document"),
+            id: None,
+            trigger: Some(String::from("find")),
+            keep_accents: false,
+        };
+
+        let json = r#"{"text":"document","trigger":"find"}"#;
+        assert_eq!(json, serde_json::to_string(&query).unwrap());
+        assert_eq!(query, serde_json::from_str(json).unwrap());
+    }
+
+    #[test]
+    fn accent_sensitive_query_is_tagged() {
+        let query = Query {
+            keep_accents: true,
+            ..Query::from("écran")
+        };
+
+        let json = r#"{"text":"écran","keep_accents":true}"#;
+        assert_eq!(json, serde_json::to_string(&query).unwrap());
+        assert_eq!(query, serde_json::from_str(json).unwrap());
+    }
+
+    #[test]
+    fn update_is_tagged_only_with_an_id() {
+        let untagged = Response::Update(SearchResults::default());
+        assert_eq!(
+            r#"{"Update":[]}"#,
+            serde_json::to_string(&untagged).unwrap()
+        );
+
+        let tagged = Response::Update(SearchResults {
+            results: Vec::new(),
+            id: Some(3),
+        });
+
+        assert_eq!(
+            r#"{"Update":{"results":[],"id":3}}"#,
+            serde_json::to_string(&tagged).unwrap()
+        );
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Time taken by a plugin to reach a point in its searches, in milliseconds.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Latency {
+    /// Number of searches measured.
+    pub count: u32,
+    /// Time taken by the latest search.
+    pub last: u64,
+    /// Time taken by the slowest search.
+    pub max: u64,
+    /// Time taken by every search combined, from which the mean is derived.
+    pub total: u64,
+}
+
+impl Latency {
+    /// Records the time taken by a search.
+    pub fn record(&mut self, elapsed: Duration) {
+        let millis = elapsed.as_millis() as u64;
+        self.count += 1;
+        self.last = millis;
+        self.max = self.max.max(millis);
+        self.total += millis;
+    }
+
+    /// Mean time taken by a search, if any were measured.
+    pub fn mean(&self) -> Option<u64> {
+        self.total.checked_div(u64::from(self.count))
+    }
+}
+
+/// Latency and health of a plugin, since it was loaded by the service.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PluginStats {
+    pub plugin: String,
+    /// Searches sent to the plugin.
+    pub searches: u32,
+    /// Time from a search being sent to the first result of the plugin.
+    pub first_result: Latency,
+    /// Time from a search being sent to the plugin finishing it.
+    pub finished: Latency,
+    /// Searches which the plugin failed to finish in time.
+    pub timeouts: u32,
+    /// Times the plugin's process exited with an error, or could not be launched.
+    pub crashes: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_summarizes_measurements() {
+        let mut latency = Latency::default();
+        assert_eq!(None, latency.mean());
+
+        for millis in [30, 10, 20] {
+            latency.record(Duration::from_millis(millis));
+        }
+
+        assert_eq!(
+            Latency {
+                count: 3,
+                last: 20,
+                max: 30,
+                total: 60,
+            },
+            latency
+        );
+
+        assert_eq!(Some(20), latency.mean());
+    }
+}
@@ -77,33 +77,31 @@ impl PluginExt for WhatIsPlugin {
     // Note that we need to send `PluginResponse::Finished` once we are done,
     // otherwise pop-launcher will not display our search results and wait forever.
     async fn search(&mut self, query: &str) {
-        // pop-launcher will only dispatch query matching the regex defined in our `plugin.ron`
-        // file, can safely strip it out.
-        let query = query.strip_prefix("whatis ");
-
-        if let Some(query) = query {
-            // Whenever we get a new query, pass the query to the `whatis` helper function
-            // and update our plugin entries with the result.
-            match run_whatis(query) {
-                Ok(entries) => self.entries = entries,
-                // If we need to produce log, we use the tracing macros.
-                Err(err) => tracing::error!("Error while running 'whatis' command: {err}"),
-            }
+        // The regex defined in our `plugin.ron` file captures the text following
+        // `whatis ` in a `query` group, so pop-launcher only sends us that text.
+        // Whenever we get a new query, pass the query to the `whatis` helper function
+        // and update our plugin entries with the result.
+        match run_whatis(query) {
+            Ok(entries) => self.entries = entries,
+            // If we need to produce log, we use the tracing macros.
+            Err(err) => tracing::error!("Error while running 'whatis' command: {err}"),
+        }
 
-            // Now we send our entries back to the launcher. We also need a way to find our entry on activation
-            // requests, here we use the entry index as an idendifier.
-            for (idx, (cmd, description)) in self.entries.iter().enumerate() {
-                self.respond_with(PluginResponse::Append(PluginSearchResult {
-                    id: idx as u32,
-                    name: format!("{cmd} - {description}"),
-                    keywords: None,
-                    description: description.clone(),
-                    icon: None,
-                    exec: None,
-                    window: None,
-                }))
-                .await;
-            }
+        // Now we send our entries back to the launcher. We also need a way to find our entry on activation
+        // requests, here we use the entry index as an idendifier.
+        for (idx, (cmd, description)) in self.entries.iter().enumerate() {
+            self.respond_with(PluginResponse::Append(PluginSearchResult {
+                id: idx as u32,
+                name: format!("{cmd} - {description}"),
+                keywords: None,
+                description: description.clone(),
+                icon: None,
+                exec: None,
+                window: None,
+                query_id: None,
+                dedupe_key: None,
+            }))
+            .await;
         }
 
         // Tell pop-launcher we are done with this search request.
@@ -124,7 +122,7 @@ impl PluginExt for WhatIsPlugin {
 
 // Now we just need to call the `run` function to start our plugin.
 // You can test it by writing request to its stdin.
-// For instance issuing a search request : `{ "Search": "whatis git" }`,
+// For instance issuing a search request : `{ "Search": "git" }`,
 // or activate one of the search results : `{ "Activate": 0 }`
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
@@ -49,9 +49,8 @@
 //!   // Respond to `pop-launcher` 'search' query
 //!   async fn search(&mut self, query: &str) {
 //!      // `pop-launcher` dispatches request to plugins according to the regex defined in
-//!      // the `plugin.ron` config file, here we get rid of the prefix
-//!      // before processing the request.
-//!      let query = query.strip_prefix("plug ").unwrap();
+//!      // the `plugin.ron` config file. A regex such as `^plug (?P<query>.*)` captures
+//!      // the text after the prefix, which is all that the plugin receives.
 //!
 //!      // Iterate through our internal search results with their indices.
 //!      let search_results = self.data.iter()
@@ -60,15 +59,10 @@
 //!
 //!      // Send our search results to `pop-launcher` using their indices as id.
 //!      for (idx, search_result) in search_results {
-//!         self.respond_with(PluginResponse::Append(PluginSearchResult {
-//!             id: idx as u32,
-//!             name: search_result.clone(),
-//!             description: "".to_string(),
-//!             keywords: None,
-//!             icon: None,
-//!             exec: None,
-//!             window: None,
-//!         })).await;
+//!         self.respond_with(PluginResponse::Append(PluginSearchResult::new(
+//!             idx as u32,
+//!             search_result.clone(),
+//!         ))).await;
 //!      }
 //!
 //!     // tell `pop-launcher` we are done with this request
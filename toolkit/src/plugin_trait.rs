@@ -89,6 +89,10 @@ where
                         self.exit();
                         break;
                     }
+                    // Sent only to plugins which introduced themselves
+                    Request::Hello(_) => (),
+                    // Sent only to the service
+                    Request::History { .. } | Request::Page { .. } | Request::Stats => (),
                 },
                 Err(why) => tracing::error!("Malformed json request: {why}"),
             }